@@ -0,0 +1,69 @@
+//! Throughput of UTF-8 validation against the subsets, next to
+//! `core::str::from_utf8`, over ASCII and non-ASCII BMP text.
+//!
+//! Run with `cargo bench`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rfc9839_rs::{UnicodeAssignables, UnicodeScalars, XmlCharacters};
+
+const SIZE: usize = 1 << 20;
+
+fn input(sample: &str) -> String {
+    sample.repeat(SIZE / sample.len() + 1)
+}
+
+/// Runs `f` over `bytes` for about a second and prints its throughput
+fn bench(name: &str, bytes: &[u8], f: impl Fn(&[u8]) -> bool) {
+    assert!(f(bytes), "{name} rejected its input");
+    let mut runs = 0u32;
+    let start = Instant::now();
+    while start.elapsed() < Duration::from_secs(1) {
+        black_box(f(black_box(bytes)));
+        runs += 1;
+    }
+    let per_run = start.elapsed() / runs;
+    let throughput = bytes.len() as f64 / per_run.as_secs_f64() / (1 << 30) as f64;
+    println!("{name:<28} {per_run:>12.2?} {throughput:>8.2} GiB/s");
+}
+
+fn main() {
+    let inputs = [
+        (
+            "ascii",
+            input("The quick brown fox\tjumps over the lazy dog.\r\n"),
+        ),
+        (
+            "latin-1",
+            input("Voil\u{e0} l'\u{e9}t\u{e9}, \u{e7}a d\u{e9}m\u{e9}nage \u{e0} Montr\u{e9}al. "),
+        ),
+        (
+            "cjk",
+            input(
+                "\u{6587}\u{5b57}\u{5316}\u{3051}\u{3092}\u{9632}\u{3050}\u{6a19}\u{6e96}\u{3002}",
+            ),
+        ),
+        (
+            "mixed bmp",
+            input(
+                "\u{3b1}\u{3b2}\u{3b3} \u{416}\u{416} \u{5d0}\u{5d1} \u{e01}\u{e02} abc, \u{20ac}5\n",
+            ),
+        ),
+    ];
+    for (name, text) in &inputs {
+        let bytes = text.as_bytes();
+        bench(&format!("{name}/from_utf8"), bytes, |b| {
+            core::str::from_utf8(b).is_ok()
+        });
+        bench(&format!("{name}/scalars"), bytes, |b| {
+            UnicodeScalars::validate_utf8(b).is_ok()
+        });
+        bench(&format!("{name}/xml"), bytes, |b| {
+            XmlCharacters::validate_utf8(b).is_ok()
+        });
+        bench(&format!("{name}/assignables"), bytes, |b| {
+            UnicodeAssignables::validate_utf8(b).is_ok()
+        });
+    }
+}
@@ -0,0 +1,265 @@
+//! Command line parsing
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use rfc9839_rs::{Profile, Replacement};
+
+use crate::report::Format;
+
+pub const USAGE: &str = "\
+Usage: rfc9839 [OPTIONS] [PATH]...
+
+Scans files, directories (recursively) and standard input for code points
+outside of an RFC9839 subset. Reads standard input when no path is given, or
+for a path of `-`. Hidden files and directories are skipped unless named
+explicitly, and so are binary files, with a NUL byte in their first 8000
+bytes.
+
+Options:
+  -p, --profile <NAME>  Subset to enforce: scalars, xml or assignables
+                        [default: assignables]
+      --format <FORMAT> Output format of the violations: text, json, sarif or
+                        github [default: text]
+      --fix             Rewrite files without their violations, standard
+                        input being fixed to standard output
+      --replace-with <CHAR>
+                        Character violations are replaced with by --fix
+                        [default: U+FFFD]
+      --remove          Remove violations instead of replacing them
+      --repair-utf8     Also fix files that aren't valid UTF-8, replacing or
+                        removing their invalid sequences. Without it, --fix
+                        reports and leaves those files as they are
+      --backup          Copy files to <PATH>.bak before fixing them
+      --dry-run         Print the fixes as a unified diff instead of applying
+                        them
+  -h, --help            Print this help
+  -V, --version         Print the version
+
+Exits with 1 when a violation was found, would be fixed by --dry-run or was
+left in a file that isn't valid UTF-8, and 2 on errors.";
+
+/// What the command line asks for
+#[derive(Debug, PartialEq, Eq)]
+pub enum Command {
+    Scan(Options),
+    Help,
+    Version,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Options {
+    pub profile: Profile,
+    pub format: Format,
+    /// How to fix violations, when they aren't only reported
+    pub fix: Option<Fix>,
+    /// Paths to scan, `-` standing for standard input
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fix {
+    pub replacement: Replacement,
+    pub backup: bool,
+    pub dry_run: bool,
+    /// Whether files that aren't valid UTF-8 are fixed too
+    pub repair_utf8: bool,
+}
+
+/// Parses the command line arguments, without the name of the program
+pub fn parse(args: impl IntoIterator<Item = OsString>) -> Result<Command, String> {
+    let mut options = Options {
+        profile: Profile::Assignables,
+        format: Format::Text,
+        fix: None,
+        paths: Vec::new(),
+    };
+    let mut fix = Fix {
+        replacement: Replacement::ReplacementCharacter,
+        backup: false,
+        dry_run: false,
+        repair_utf8: false,
+    };
+    let mut fixing = false;
+    // the last option only making sense along with `--fix`
+    let mut fix_flag = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let Some(flag) = arg.to_str().filter(|a| a.starts_with('-') && *a != "-") else {
+            options.paths.push(arg.into());
+            continue;
+        };
+        let (flag, inline) = match flag.split_once('=') {
+            Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_owned())),
+            _ => (flag, None),
+        };
+        let mut value = || match inline.clone() {
+            Some(value) => Ok(value),
+            None => args
+                .next()
+                .and_then(|v| v.into_string().ok())
+                .ok_or_else(|| format!("missing value for {flag}")),
+        };
+        match flag {
+            "--" => {
+                options.paths.extend(args.by_ref().map(PathBuf::from));
+            }
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "-p" | "--profile" => {
+                let name = value()?;
+                options.profile = name
+                    .parse()
+                    .map_err(|_| format!("unknown profile `{name}`"))?;
+            }
+            "--format" => {
+                let name = value()?;
+                options.format = name
+                    .parse()
+                    .map_err(|()| format!("unknown format `{name}`"))?;
+            }
+            "--fix" => fixing = true,
+            "--replace-with" => {
+                let value = value()?;
+                let mut chars = value.chars();
+                let (Some(c), None) = (chars.next(), chars.next()) else {
+                    return Err(format!("expected a single character, not `{value}`"));
+                };
+                // would add lines, which the diffs of --dry-run pair by position
+                if c == '\n' || c == '\r' {
+                    return Err("violations can't be replaced with a line break".into());
+                }
+                fix.replacement = Replacement::Char(c);
+                fix_flag = Some(flag.to_owned());
+            }
+            "--remove" => {
+                fix.replacement = Replacement::Remove;
+                fix_flag = Some(flag.to_owned());
+            }
+            "--backup" => {
+                fix.backup = true;
+                fix_flag = Some(flag.to_owned());
+            }
+            "--dry-run" => {
+                fix.dry_run = true;
+                fix_flag = Some(flag.to_owned());
+            }
+            "--repair-utf8" => {
+                fix.repair_utf8 = true;
+                fix_flag = Some(flag.to_owned());
+            }
+            _ => return Err(format!("unknown option `{flag}`")),
+        }
+    }
+    if fixing && options.format != Format::Text {
+        return Err("--format only applies to violations reported without --fix".into());
+    }
+    if fixing {
+        let replacement = fix.replacement.as_char();
+        if let Some(c) = replacement.filter(|&c| !options.profile.contains(c as u32)) {
+            return Err(format!(
+                "U+{:04X} can't replace violations, it isn't part of the {} profile",
+                c as u32, options.profile
+            ));
+        }
+        options.fix = Some(fix);
+    } else if let Some(flag) = fix_flag {
+        return Err(format!("{flag} requires --fix"));
+    }
+    if options.paths.is_empty() {
+        options.paths.push("-".into());
+    }
+    Ok(Command::Scan(options))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse_args(args: &[&str]) -> Result<Command, String> {
+        parse(args.iter().map(OsString::from))
+    }
+
+    #[test]
+    fn test_parse() {
+        let scan = |profile, paths: &[&str]| {
+            Ok(Command::Scan(Options {
+                profile,
+                format: Format::Text,
+                fix: None,
+                paths: paths.iter().map(PathBuf::from).collect(),
+            }))
+        };
+        assert_eq!(parse_args(&[]), scan(Profile::Assignables, &["-"]));
+        assert_eq!(
+            parse_args(&["a.txt", "-p", "xml", "dir"]),
+            scan(Profile::XmlCharacters, &["a.txt", "dir"])
+        );
+        assert_eq!(
+            parse_args(&["--profile=scalars", "-", "--", "-p"]),
+            scan(Profile::Scalars, &["-", "-p"])
+        );
+        assert_eq!(parse_args(&["x", "--help"]), Ok(Command::Help));
+        assert_eq!(parse_args(&["-V"]), Ok(Command::Version));
+        assert!(parse_args(&["--profile"]).is_err());
+        assert!(parse_args(&["-p", "ascii"]).is_err());
+        assert!(parse_args(&["--fast"]).is_err());
+
+        match parse_args(&["--format", "sarif"]) {
+            Ok(Command::Scan(options)) => assert_eq!(options.format, Format::Sarif),
+            other => panic!("{:?}", other),
+        }
+        assert!(parse_args(&["--format=xml"]).is_err());
+        assert!(parse_args(&["--format=json", "--fix"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_fix() {
+        let fix = |args: &[&str]| match parse_args(args) {
+            Ok(Command::Scan(options)) => Ok(options.fix),
+            Ok(command) => panic!("{:?}", command),
+            Err(e) => Err(e),
+        };
+        let default = Fix {
+            replacement: Replacement::ReplacementCharacter,
+            backup: false,
+            dry_run: false,
+            repair_utf8: false,
+        };
+        assert_eq!(fix(&["dir"]), Ok(None));
+        assert_eq!(fix(&["--fix", "dir"]), Ok(Some(default)));
+        assert_eq!(
+            fix(&["--dry-run", "--remove", "--fix"]),
+            Ok(Some(Fix {
+                replacement: Replacement::Remove,
+                dry_run: true,
+                ..default
+            }))
+        );
+        assert_eq!(
+            fix(&["--fix", "--backup", "--replace-with=?"]),
+            Ok(Some(Fix {
+                replacement: Replacement::Char('?'),
+                backup: true,
+                ..default
+            }))
+        );
+        assert_eq!(
+            fix(&["--repair-utf8", "--fix"]),
+            Ok(Some(Fix {
+                repair_utf8: true,
+                ..default
+            }))
+        );
+        assert_eq!(fix(&["--backup"]), Err("--backup requires --fix".into()));
+        assert!(fix(&["--repair-utf8"]).is_err());
+        assert!(fix(&["--fix", "--replace-with", "ab"]).is_err());
+        assert_eq!(
+            fix(&["--fix", "--replace-with", "\n"]),
+            Err("violations can't be replaced with a line break".into())
+        );
+        assert!(fix(&["--fix", "--replace-with=\r"]).is_err());
+        assert!(fix(&["--fix", "--replace-with", "\u{7f}"]).is_err());
+        assert!(fix(&["--fix", "-p", "scalars", "--replace-with", "\u{7f}"]).is_ok());
+    }
+}
@@ -0,0 +1,133 @@
+//! Unified diffs of fixed files
+
+use std::io::{self, Write};
+use std::path::Path;
+
+/// Unchanged lines shown around each change
+const CONTEXT: usize = 3;
+
+/// Lines of `bytes` without their line feed, along with whether they had one
+fn lines(bytes: &[u8]) -> Vec<(&[u8], bool)> {
+    bytes
+        .split_inclusive(|&b| b == b'\n')
+        .map(|line| match line.strip_suffix(b"\n") {
+            Some(line) => (line, true),
+            None => (line, false),
+        })
+        .collect()
+}
+
+fn write_line(out: &mut impl Write, prefix: u8, (line, newline): (&[u8], bool)) -> io::Result<()> {
+    out.write_all(&[prefix])?;
+    out.write_all(line)?;
+    out.write_all(b"\n")?;
+    if !newline {
+        out.write_all(b"\\ No newline at end of file\n")?;
+    }
+    Ok(())
+}
+
+/// Line range of a hunk header, an empty range starting at the line before
+fn range(start: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", start),
+        _ => format!("{},{}", start + 1, len),
+    }
+}
+
+/// Writes the unified diff from `old` to `new` for the file at `path`.
+///
+/// Lines are compared by position: fixes never add or remove a line feed, so
+/// line `n` of `new` is line `n` of `old` fixed. Only a last line without a
+/// line feed can disappear.
+pub fn unified_diff(path: &Path, old: &[u8], new: &[u8], out: &mut impl Write) -> io::Result<()> {
+    let (old, new) = (lines(old), lines(new));
+    let changed: Vec<usize> = (0..old.len().max(new.len()))
+        .filter(|&i| old.get(i) != new.get(i))
+        .collect();
+    if changed.is_empty() {
+        return Ok(());
+    }
+    writeln!(out, "--- {}", path.display())?;
+    writeln!(out, "+++ {}", path.display())?;
+
+    let mut next = 0;
+    while next < changed.len() {
+        // changes closer than twice the context share a hunk
+        let first = changed[next];
+        let mut last = first;
+        next += 1;
+        while next < changed.len() && changed[next] <= last + 2 * CONTEXT + 1 {
+            last = changed[next];
+            next += 1;
+        }
+        let start = first.saturating_sub(CONTEXT);
+        let end = last + CONTEXT + 1;
+        let (old_end, new_end) = (end.min(old.len()), end.min(new.len()));
+        writeln!(
+            out,
+            "@@ -{} +{} @@",
+            range(start, old_end - start),
+            range(start, new_end - start)
+        )?;
+
+        let mut line = start;
+        while line < old_end.max(new_end) {
+            if old.get(line) == new.get(line) {
+                write_line(out, b' ', old[line])?;
+                line += 1;
+                continue;
+            }
+            let run = (line..end)
+                .find(|&i| old.get(i) == new.get(i))
+                .unwrap_or(end);
+            for &removed in &old[line..run.min(old_end)] {
+                write_line(out, b'-', removed)?;
+            }
+            for &added in &new[line..run.min(new_end)] {
+                write_line(out, b'+', added)?;
+            }
+            line = run;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn diff(old: &[u8], new: &[u8]) -> String {
+        let mut out = Vec::new();
+        unified_diff(Path::new("f.txt"), old, new, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_unified_diff() {
+        assert_eq!(diff(b"same\n", b"same\n"), "");
+
+        let old = b"1\n2\n3\n4\nfive\n6\n7\n8\n9\n10\n11\n12\n13\n14\nlast";
+        let new = b"1\n2\n3\n4\nfiv\n6\n7\n8\n9\n10\n11\n12\n13\n14\nlas";
+        assert_eq!(
+            diff(old, new),
+            "--- f.txt\n+++ f.txt\n\
+             @@ -2,7 +2,7 @@\n 2\n 3\n 4\n-five\n+fiv\n 6\n 7\n 8\n\
+             @@ -12,4 +12,4 @@\n 12\n 13\n 14\n-last\n\\ No newline at end of file\n\
+             +las\n\\ No newline at end of file\n"
+        );
+
+        assert_eq!(
+            diff(b"a\nb\nc\n", b"A\nB\nc\n"),
+            "--- f.txt\n+++ f.txt\n@@ -1,3 +1,3 @@\n-a\n-b\n+A\n+B\n c\n"
+        );
+        assert_eq!(
+            diff(b"a\r\nb", b"a\r\n"),
+            "--- f.txt\n+++ f.txt\n@@ -1,2 +1,1 @@\n a\r\n-b\n\\ No newline at end of file\n"
+        );
+        assert_eq!(
+            diff(b"b", b""),
+            "--- f.txt\n+++ f.txt\n@@ -1,1 +0,0 @@\n-b\n\\ No newline at end of file\n"
+        );
+    }
+}
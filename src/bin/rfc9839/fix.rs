@@ -0,0 +1,142 @@
+//! Rewriting of files without their violations
+
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use rfc9839_rs::{Profile, Replacement};
+
+use crate::scan;
+
+/// `bytes` with `replacement` applied to every violation of `profile`, along
+/// with the number of violations. Each invalid UTF-8 sequence counts as one
+/// violation. Line endings and a byte order mark are left as they are, as
+/// every profile allows them.
+pub fn fix(bytes: &[u8], profile: Profile, replacement: Replacement) -> (Vec<u8>, usize) {
+    let mut buf = [0; 4];
+    let replacement: &[u8] = match replacement.as_char() {
+        Some(c) => c.encode_utf8(&mut buf).as_bytes(),
+        None => &[],
+    };
+    let mut fixed = Vec::with_capacity(bytes.len());
+    let mut start = 0;
+    let mut count = 0;
+    for error in scan::violations(bytes, profile) {
+        fixed.extend_from_slice(&bytes[start..error.offset]);
+        fixed.extend_from_slice(replacement);
+        start = error.offset + error.len;
+        count += 1;
+    }
+    fixed.extend_from_slice(&bytes[start..]);
+    (fixed, count)
+}
+
+/// Path the content of `path` is backed up to before being fixed
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    backup.into()
+}
+
+/// Replaces the content of the file at `path` with `bytes`, by renaming a
+/// temporary file from the same directory over it, so that readers either see
+/// the old or the new content. With `backup`, the old content is copied to
+/// [`backup_path`] first. Symbolic links are followed to the file they point
+/// to, the backup being written next to `path` as given.
+pub fn write_atomic(path: &Path, bytes: &[u8], backup: bool) -> io::Result<()> {
+    let target = fs::canonicalize(path)?;
+    let (Some(dir), Some(name)) = (target.parent(), target.file_name()) else {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, "not a file"));
+    };
+    let mut tmp = OsString::from(".");
+    tmp.push(name);
+    tmp.push(format!(".{}.tmp", process::id()));
+    let tmp = dir.join(tmp);
+
+    let file = OpenOptions::new().write(true).create_new(true).open(&tmp)?;
+    let replaced = write_synced(file, bytes, fs::metadata(&target)?.permissions()).and_then(|()| {
+        if backup {
+            fs::copy(&target, backup_path(path))?;
+        }
+        fs::rename(&tmp, &target)
+    });
+    if replaced.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    replaced
+}
+
+fn write_synced(mut file: File, bytes: &[u8], permissions: fs::Permissions) -> io::Result<()> {
+    file.write_all(bytes)?;
+    file.set_permissions(permissions)?;
+    file.sync_all()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fix() {
+        let input = b"\xef\xbb\xbfa\x7f\r\nb\xed\xa0\x80\xc2\x85\r\n\xe2\x82";
+        let (fixed, count) = fix(input, Profile::Assignables, Replacement::Remove);
+        assert_eq!(
+            (fixed.as_slice(), count),
+            (&b"\xef\xbb\xbfa\r\nb\r\n"[..], 6)
+        );
+
+        let (fixed, count) = fix(input, Profile::Scalars, Replacement::Char('?'));
+        assert_eq!(fixed, b"\xef\xbb\xbfa\x7f\r\nb???\xc2\x85\r\n?");
+        assert_eq!(count, 4);
+
+        let (fixed, count) = fix(b"ok\n", Profile::Assignables, Replacement::Remove);
+        assert_eq!((fixed.as_slice(), count), (&b"ok\n"[..], 0));
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = std::env::temp_dir().join(format!("rfc9839-fix-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.txt");
+        fs::write(&path, "old").unwrap();
+
+        write_atomic(&path, b"new", false).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert!(!backup_path(&path).exists());
+
+        write_atomic(&path, b"newer", true).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"newer");
+        assert_eq!(fs::read(backup_path(&path)).unwrap(), b"new");
+
+        let entries = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(entries, 2);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_symlink() {
+        let dir = std::env::temp_dir().join(format!("rfc9839-fix-link-{}", process::id()));
+        fs::create_dir_all(dir.join("target")).unwrap();
+        let target = dir.join("target/data.txt");
+        let link = dir.join("link.txt");
+        fs::write(&target, "old").unwrap();
+        std::os::unix::fs::symlink(&target, &link).unwrap();
+
+        write_atomic(&link, b"new", true).unwrap();
+        let is_link = fs::symlink_metadata(&link)
+            .unwrap()
+            .file_type()
+            .is_symlink();
+        let backup = fs::read(backup_path(&link));
+        let target_backup = backup_path(&target).exists();
+        let written = fs::read(&target);
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(is_link);
+        assert_eq!(written.unwrap(), b"new");
+        assert_eq!(backup.unwrap(), b"old");
+        assert!(!target_backup);
+    }
+}
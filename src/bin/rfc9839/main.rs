@@ -0,0 +1,237 @@
+//! Scans files, directories or standard input for code points outside of an
+//! RFC9839 subset
+
+mod args;
+mod diff;
+mod fix;
+mod report;
+mod scan;
+
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::str;
+
+use rfc9839_rs::Profile;
+
+use crate::args::{Command, Fix, Options, USAGE};
+use crate::report::Reporter;
+
+/// Outcome of a run, ordered by precedence of the exit code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Status {
+    Clean = 0,
+    Found = 1,
+    Failed = 2,
+}
+
+struct Scanner<W> {
+    profile: Profile,
+    fix: Option<Fix>,
+    report: Reporter<W>,
+    status: Status,
+}
+
+impl<W: Write> Scanner<W> {
+    /// Reports an error reading `path` and carries on with the other paths
+    fn fail(&mut self, path: &Path, e: io::Error) -> io::Result<()> {
+        eprintln!("rfc9839: {}: {}", path.display(), e);
+        self.status = self.status.max(Status::Failed);
+        Ok(())
+    }
+
+    /// Scans `path`, recursing into directories. Entries starting with a dot
+    /// and symbolic links to directories are skipped while recursing.
+    fn path(&mut self, path: &Path) -> io::Result<()> {
+        if path == Path::new("-") {
+            let mut bytes = Vec::new();
+            return match io::stdin().lock().read_to_end(&mut bytes) {
+                Ok(_) => self.bytes(None, &bytes),
+                Err(e) => self.fail(path, e),
+            };
+        }
+        match fs::metadata(path) {
+            Ok(meta) if meta.is_dir() => self.dir(path),
+            Ok(_) => match fs::read(path) {
+                Ok(bytes) => self.bytes(Some(path), &bytes),
+                Err(e) => self.fail(path, e),
+            },
+            Err(e) => self.fail(path, e),
+        }
+    }
+
+    fn dir(&mut self, path: &Path) -> io::Result<()> {
+        let mut entries = match fs::read_dir(path).and_then(|d| d.collect::<io::Result<Vec<_>>>()) {
+            Ok(entries) => entries,
+            Err(e) => return self.fail(path, e),
+        };
+        entries.retain(|e| !e.file_name().as_encoded_bytes().starts_with(b"."));
+        entries.sort_by_key(|e| e.file_name());
+        for entry in entries {
+            let path = entry.path();
+            if entry.file_type().is_ok_and(|t| t.is_symlink()) && path.is_dir() {
+                continue;
+            }
+            self.path(&path)?;
+        }
+        Ok(())
+    }
+
+    /// Reports or fixes the violations of `bytes`, read from `path` or from
+    /// standard input. Binary files are skipped. Only errors writing to
+    /// standard output are returned.
+    fn bytes(&mut self, path: Option<&Path>, bytes: &[u8]) -> io::Result<()> {
+        if let Some(path) = path.filter(|_| scan::is_binary(bytes)) {
+            eprintln!("rfc9839: {}: skipped binary file", path.display());
+            return Ok(());
+        }
+        let name = path.unwrap_or(Path::new(report::STDIN));
+        let Some(fix) = self.fix else {
+            for finding in scan::scan(bytes, self.profile) {
+                self.report.finding(path, &finding)?;
+                self.status = self.status.max(Status::Found);
+            }
+            return Ok(());
+        };
+        if !fix.repair_utf8 && str::from_utf8(bytes).is_err() {
+            return self.unrepaired(path, bytes, fix);
+        }
+        let (fixed, count) = fix::fix(bytes, self.profile, fix.replacement);
+        if fix.dry_run {
+            if count > 0 {
+                diff::unified_diff(name, bytes, &fixed, self.report.out())?;
+                self.status = self.status.max(Status::Found);
+            }
+            return Ok(());
+        }
+        match path {
+            None => self.report.out().write_all(&fixed),
+            Some(path) if count > 0 => match fix::write_atomic(path, &fixed, fix.backup) {
+                Ok(()) => {
+                    let s = if count == 1 { "" } else { "s" };
+                    eprintln!(
+                        "rfc9839: {}: fixed {} violation{}",
+                        path.display(),
+                        count,
+                        s
+                    );
+                    Ok(())
+                }
+                Err(e) => self.fail(path, e),
+            },
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// Reports the invalid UTF-8 of `bytes`, which are left as they are as
+    /// `fix` doesn't repair it. Standard input is copied to standard output.
+    fn unrepaired(&mut self, path: Option<&Path>, bytes: &[u8], fix: Fix) -> io::Result<()> {
+        let name = path.unwrap_or(Path::new(report::STDIN));
+        // every valid character is a Unicode scalar, leaving invalid UTF-8
+        for finding in scan::scan(bytes, Profile::Scalars) {
+            eprintln!("rfc9839: {}:{}", name.display(), finding);
+        }
+        eprintln!(
+            "rfc9839: {}: not valid UTF-8, left unchanged without --repair-utf8",
+            name.display()
+        );
+        self.status = self.status.max(Status::Found);
+        match path {
+            None if !fix.dry_run => self.report.out().write_all(bytes),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn run(options: Options) -> Status {
+    let mut scanner = Scanner {
+        profile: options.profile,
+        fix: options.fix,
+        report: Reporter::new(
+            options.format,
+            options.profile,
+            BufWriter::new(io::stdout().lock()),
+        ),
+        status: Status::Clean,
+    };
+    let written = options
+        .paths
+        .iter()
+        .try_for_each(|path| scanner.path(path))
+        .and_then(|()| scanner.report.finish());
+    match written {
+        Ok(()) => scanner.status,
+        // the reader went away, as with `rfc9839 dir | head`
+        Err(e) if e.kind() == io::ErrorKind::BrokenPipe => scanner.status,
+        Err(e) => {
+            eprintln!("rfc9839: {}", e);
+            Status::Failed
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let status = match args::parse(std::env::args_os().skip(1)) {
+        Ok(Command::Scan(options)) => run(options),
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            Status::Clean
+        }
+        Ok(Command::Version) => {
+            println!("rfc9839 {}", env!("CARGO_PKG_VERSION"));
+            Status::Clean
+        }
+        Err(e) => {
+            eprintln!("rfc9839: {}\n\n{}", e, USAGE);
+            Status::Failed
+        }
+    };
+    ExitCode::from(status as u8)
+}
+
+#[cfg(test)]
+mod test {
+    use rfc9839_rs::Replacement;
+
+    use super::*;
+    use crate::report::Format;
+
+    fn fix_dir(dir: &Path, repair_utf8: bool) -> Status {
+        let fix = Fix {
+            replacement: Replacement::ReplacementCharacter,
+            backup: false,
+            dry_run: false,
+            repair_utf8,
+        };
+        let mut scanner = Scanner {
+            profile: Profile::Assignables,
+            fix: Some(fix),
+            report: Reporter::new(Format::Text, Profile::Assignables, Vec::new()),
+            status: Status::Clean,
+        };
+        scanner.path(dir).unwrap();
+        scanner.status
+    }
+
+    #[test]
+    fn test_fix_invalid_utf8() {
+        let dir = std::env::temp_dir().join(format!("rfc9839-main-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (latin1, text) = (dir.join("latin1.txt"), dir.join("text.txt"));
+        fs::write(&latin1, b"caf\xe9\x7f\n").unwrap();
+        fs::write(&text, "caf\u{e9}\u{7f}\n").unwrap();
+
+        let status = fix_dir(&dir, false);
+        let left = fs::read(&latin1).unwrap();
+        let fixed = fs::read_to_string(&text).unwrap();
+        let repaired = (fix_dir(&dir, true), fs::read_to_string(&latin1));
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(status, Status::Found);
+        assert_eq!(left, b"caf\xe9\x7f\n");
+        assert_eq!(fixed, "caf\u{e9}\u{fffd}\n");
+        assert_eq!(repaired.0, Status::Clean);
+        assert_eq!(repaired.1.unwrap(), "caf\u{fffd}\u{fffd}\n");
+    }
+}
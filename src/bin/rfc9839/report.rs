@@ -0,0 +1,366 @@
+//! Output formats of the violations found
+
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::path::Path;
+use std::str::FromStr;
+
+use rfc9839_rs::{Profile, Violation};
+use serde_json::{Value, json};
+
+use crate::scan::Finding;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    /// `path:line:column: reason` lines
+    Text,
+    /// An array of violation objects
+    Json,
+    /// A SARIF 2.1.0 log, for code scanning dashboards
+    Sarif,
+    /// GitHub Actions workflow commands, annotating pull requests
+    Github,
+}
+
+impl FromStr for Format {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "sarif" => Ok(Format::Sarif),
+            "github" => Ok(Format::Github),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Name standard input is reported under
+pub const STDIN: &str = "<stdin>";
+
+/// Escapes the value of a GitHub workflow command, or of one of its
+/// properties with `property`
+fn github_escape(s: &str, property: bool) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '%' => escaped.push_str("%25"),
+            '\r' => escaped.push_str("%0D"),
+            '\n' => escaped.push_str("%0A"),
+            ':' if property => escaped.push_str("%3A"),
+            ',' if property => escaped.push_str("%2C"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Base of the relative URIs of SARIF artifact locations, the current
+/// directory
+const SRCROOT: &str = "%SRCROOT%";
+
+/// `path` percent-encoded as the path of a URI, with `/` separators
+fn uri_path(path: &Path) -> String {
+    let path = path.to_string_lossy();
+    let mut uri = String::with_capacity(path.len());
+    for b in path.bytes() {
+        match b {
+            b'\\' if cfg!(windows) => uri.push('/'),
+            // the colon of drive letters
+            b':' if cfg!(windows) => uri.push(':'),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(b as char)
+            }
+            b => {
+                let _ = write!(uri, "%{:02X}", b);
+            }
+        }
+    }
+    uri
+}
+
+/// `file` URI of the absolute `path`
+fn file_uri(path: &Path) -> String {
+    let path = uri_path(path);
+    // `C:/dir` on Windows
+    let slash = if path.starts_with('/') { "" } else { "/" };
+    format!("file://{}{}", slash, path)
+}
+
+/// SARIF artifact location of `path`, relative to [`SRCROOT`] unless it is
+/// absolute, or of standard input for `None`
+fn artifact_location(path: Option<&Path>) -> Value {
+    match path {
+        None => json!({ "description": { "text": "standard input" } }),
+        Some(path) if path.is_absolute() => json!({ "uri": file_uri(path) }),
+        Some(path) => {
+            let path = path.strip_prefix(".").unwrap_or(path);
+            json!({ "uri": uri_path(path), "uriBaseId": SRCROOT })
+        }
+    }
+}
+
+/// Writes findings in a [`Format`]. The formats made of a single document
+/// are only written by [`Reporter::finish`].
+pub struct Reporter<W> {
+    format: Format,
+    profile: Profile,
+    out: W,
+    results: Vec<Value>,
+    rules: Vec<Violation>,
+}
+
+impl<W: Write> Reporter<W> {
+    pub fn new(format: Format, profile: Profile, out: W) -> Self {
+        Self {
+            format,
+            profile,
+            out,
+            results: Vec::new(),
+            rules: Vec::new(),
+        }
+    }
+
+    /// Output of the reporter, for what isn't a finding
+    pub fn out(&mut self) -> &mut W {
+        &mut self.out
+    }
+
+    /// Reports a finding of the file at `path`, or of standard input for
+    /// `None`
+    pub fn finding(&mut self, path: Option<&Path>, finding: &Finding) -> io::Result<()> {
+        let violation = finding.error.violation;
+        let name = path.map_or(STDIN.into(), |path| path.to_string_lossy());
+        match self.format {
+            Format::Text => writeln!(self.out, "{}:{}", name, finding),
+            Format::Github => writeln!(
+                self.out,
+                "::error file={},line={},col={},title={}::{}",
+                github_escape(&name, true),
+                finding.line,
+                finding.column,
+                github_escape(
+                    &format!("RFC9839 {} {}", self.profile, violation.code()),
+                    true
+                ),
+                github_escape(&finding.message(), false),
+            ),
+            Format::Json => {
+                self.results.push(json!({
+                    "file": name,
+                    "offset": finding.error.offset,
+                    "line": finding.line,
+                    "column": finding.column,
+                    "code_point": finding.error.code_point,
+                    "subset": self.profile.name(),
+                    "violation": violation.code(),
+                    "reason": violation.description(),
+                }));
+                Ok(())
+            }
+            Format::Sarif => {
+                let rule = match self.rules.iter().position(|&v| v == violation) {
+                    Some(rule) => rule,
+                    None => {
+                        self.rules.push(violation);
+                        self.rules.len() - 1
+                    }
+                };
+                self.results.push(json!({
+                    "ruleId": violation.code(),
+                    "ruleIndex": rule,
+                    "level": "error",
+                    "message": { "text": finding.message() },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": artifact_location(path),
+                            "region": {
+                                "startLine": finding.line,
+                                "startColumn": finding.column,
+                                "byteOffset": finding.error.offset,
+                            },
+                        },
+                    }],
+                    "properties": {
+                        "codePoint": finding.error.code_point,
+                        "subset": self.profile.name(),
+                    },
+                }));
+                Ok(())
+            }
+        }
+    }
+
+    /// Writes the single document formats and flushes the output
+    pub fn finish(&mut self) -> io::Result<()> {
+        let results = std::mem::take(&mut self.results);
+        let document = match self.format {
+            Format::Text | Format::Github => None,
+            Format::Json => Some(Value::Array(results)),
+            Format::Sarif => Some(self.sarif(results)),
+        };
+        if let Some(document) = document {
+            serde_json::to_writer_pretty(&mut self.out, &document)?;
+            writeln!(self.out)?;
+        }
+        self.out.flush()
+    }
+
+    fn sarif(&self, results: Vec<Value>) -> Value {
+        let rules: Vec<Value> = self
+            .rules
+            .iter()
+            .map(|&v| json!({ "id": v.code(), "shortDescription": { "text": v.description() } }))
+            .collect();
+        let mut base_ids = serde_json::Map::new();
+        if let Ok(dir) = std::env::current_dir() {
+            let uri = file_uri(&dir);
+            let slash = if uri.ends_with('/') { "" } else { "/" };
+            base_ids.insert(
+                SRCROOT.into(),
+                json!({ "uri": format!("{}{}", uri, slash) }),
+            );
+        }
+        json!({
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "version": "2.1.0",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "rfc9839",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "informationUri": env!("CARGO_PKG_REPOSITORY"),
+                        "rules": rules,
+                    },
+                },
+                "originalUriBaseIds": base_ids,
+                "columnKind": "unicodeCodePoints",
+                "results": results,
+            }],
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scan::scan;
+
+    fn report(format: Format, path: &str, bytes: &[u8]) -> String {
+        let path = Some(Path::new(path)).filter(|_| path != STDIN);
+        let mut reporter = Reporter::new(format, Profile::Assignables, Vec::new());
+        for finding in scan(bytes, Profile::Assignables) {
+            reporter.finding(path, &finding).unwrap();
+        }
+        reporter.finish().unwrap();
+        String::from_utf8(reporter.out).unwrap()
+    }
+
+    const INPUT: &[u8] = b"caf\xc3\xa9\n\x7f\xff";
+
+    #[test]
+    fn test_text_and_github() {
+        assert_eq!(
+            report(Format::Text, "a.txt", INPUT),
+            "a.txt:2:1: U+007F DEL control character\na.txt:2:2: invalid UTF-8\n"
+        );
+        assert_eq!(
+            report(Format::Github, "dir,1/a:b.txt", INPUT),
+            "::error file=dir%2C1/a%3Ab.txt,line=2,col=1,title=RFC9839 assignables Delete::U+007F DEL control character\n\
+             ::error file=dir%2C1/a%3Ab.txt,line=2,col=2,title=RFC9839 assignables InvalidUtf8::invalid UTF-8\n"
+        );
+        assert_eq!(report(Format::Text, "a.txt", b"ok"), "");
+        assert_eq!(
+            report(Format::Text, STDIN, b"\x7f"),
+            "<stdin>:1:1: U+007F DEL control character\n"
+        );
+    }
+
+    #[test]
+    fn test_json() {
+        let json: Value = serde_json::from_str(&report(Format::Json, "a.txt", INPUT)).unwrap();
+        assert_eq!(
+            json,
+            json!([
+                {
+                    "file": "a.txt", "offset": 6, "line": 2, "column": 1, "code_point": 0x7f,
+                    "subset": "assignables", "violation": "Delete",
+                    "reason": "DEL control character",
+                },
+                {
+                    "file": "a.txt", "offset": 7, "line": 2, "column": 2, "code_point": null,
+                    "subset": "assignables", "violation": "InvalidUtf8",
+                    "reason": "invalid UTF-8",
+                },
+            ])
+        );
+        assert_eq!(report(Format::Json, "a.txt", b"ok"), "[]\n");
+    }
+
+    #[test]
+    fn test_sarif() {
+        let input = b"\x7f\x7f\xc2\x85";
+        let sarif = report(Format::Sarif, "./my dir/a.txt", input);
+        let sarif: Value = serde_json::from_str(&sarif).unwrap();
+        assert_eq!(sarif["version"], "2.1.0");
+        let run = &sarif["runs"][0];
+        assert_eq!(run["tool"]["driver"]["rules"][1]["id"], "C1Control");
+        assert_eq!(run["tool"]["driver"]["rules"].as_array().unwrap().len(), 2);
+
+        let result = &run["results"][2];
+        assert_eq!(result["ruleId"], "C1Control");
+        assert_eq!(result["ruleIndex"], 1);
+        assert_eq!(
+            result["message"]["text"],
+            "U+0085 legacy C1 control character"
+        );
+        let location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(
+            location["artifactLocation"],
+            json!({ "uri": "my%20dir/a.txt", "uriBaseId": "%SRCROOT%" })
+        );
+        assert_eq!(
+            location["region"],
+            json!({ "startLine": 1, "startColumn": 3, "byteOffset": 2 })
+        );
+        assert_eq!(result["properties"]["codePoint"], 0x85);
+        let root = run["originalUriBaseIds"]["%SRCROOT%"]["uri"]
+            .as_str()
+            .unwrap();
+        assert!(
+            root.starts_with("file:///") && root.ends_with('/'),
+            "{}",
+            root
+        );
+    }
+
+    #[test]
+    fn test_sarif_locations() {
+        let location = |path| {
+            let sarif: Value = serde_json::from_str(&report(Format::Sarif, path, b"\x7f")).unwrap();
+            sarif["runs"][0]["results"][0]["locations"][0]["physicalLocation"]["artifactLocation"]
+                .clone()
+        };
+        assert_eq!(
+            location(STDIN),
+            json!({ "description": { "text": "standard input" } })
+        );
+        assert_eq!(
+            location("src/a:b#1.rs"),
+            json!({ "uri": "src/a%3Ab%231.rs", "uriBaseId": "%SRCROOT%" })
+        );
+        if cfg!(unix) {
+            assert_eq!(
+                location("/tmp/a b.txt"),
+                json!({ "uri": "file:///tmp/a%20b.txt" })
+            );
+        }
+        if cfg!(windows) {
+            assert_eq!(
+                location("C:\\dir\\a b.txt"),
+                json!({ "uri": "file:///C:/dir/a%20b.txt" })
+            );
+        }
+    }
+}
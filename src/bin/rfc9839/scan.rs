@@ -0,0 +1,191 @@
+//! Violations of a buffer, located by line and column
+
+use std::{fmt, iter, str};
+
+use rfc9839_rs::{Profile, Violation};
+
+/// Number of leading bytes looked at by [`is_binary`], as git does
+const BINARY_PROBE: usize = 8000;
+
+/// Whether `bytes` look like the content of a binary file rather than text,
+/// by a NUL byte close to their start
+pub fn is_binary(bytes: &[u8]) -> bool {
+    bytes[..bytes.len().min(BINARY_PROBE)].contains(&0)
+}
+
+/// A character outside of a profile, or an invalid UTF-8 sequence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Located {
+    /// Offset in the whole buffer
+    pub offset: usize,
+    /// Length in bytes
+    pub len: usize,
+    pub code_point: Option<u32>,
+    pub violation: Violation,
+}
+
+/// Every violation of `profile` in `bytes`, in order.
+///
+/// After a character outside of the profile, validation resumes with the next
+/// character. After malformed UTF-8, it resumes after the maximal subpart of
+/// the sequence, the bytes [`String::from_utf8_lossy`] would replace with a
+/// single U+FFFD.
+pub fn violations(bytes: &[u8], profile: Profile) -> impl Iterator<Item = Located> + '_ {
+    let mut pos = 0;
+    iter::from_fn(move || {
+        let e = profile.validate_utf8(bytes.get(pos..)?).err()?;
+        let offset = pos + e.offset();
+        let len = match e.code_point().and_then(char::from_u32) {
+            Some(c) => c.len_utf8(),
+            None => str::from_utf8(&bytes[offset..])
+                .err()
+                .and_then(|e| e.error_len())
+                .unwrap_or(bytes.len() - offset),
+        };
+        pos = offset + len;
+        Some(Located {
+            offset,
+            len,
+            code_point: e.code_point(),
+            violation: e.violation(),
+        })
+    })
+}
+
+/// A violation along with its 1-based line and column. Columns count
+/// characters, each invalid UTF-8 sequence counting as one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Finding {
+    pub line: usize,
+    pub column: usize,
+    pub error: Located,
+}
+
+impl Finding {
+    /// `U+XXXX reason`, or only the reason for invalid UTF-8
+    pub fn message(&self) -> String {
+        let reason = self.error.violation.description();
+        match self.error.code_point {
+            Some(c) => format!("U+{:04X} {}", c, reason),
+            None => reason.to_owned(),
+        }
+    }
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message())
+    }
+}
+
+/// Line and column of an offset, moved forward incrementally
+struct Cursor {
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl Cursor {
+    const fn new() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+
+    /// Moves to `offset`, which must start a character or an invalid sequence
+    fn advance(&mut self, bytes: &[u8], offset: usize) {
+        for chunk in bytes[self.offset..offset].utf8_chunks() {
+            for c in chunk.valid().chars() {
+                if c == '\n' {
+                    self.line += 1;
+                    self.column = 1;
+                } else {
+                    self.column += 1;
+                }
+            }
+            if !chunk.invalid().is_empty() {
+                self.column += 1;
+            }
+        }
+        self.offset = offset;
+    }
+}
+
+/// Every violation of `profile` in `bytes`
+pub fn scan(bytes: &[u8], profile: Profile) -> Vec<Finding> {
+    let mut cursor = Cursor::new();
+    violations(bytes, profile)
+        .map(|error| {
+            cursor.advance(bytes, error.offset);
+            Finding {
+                line: cursor.line,
+                column: cursor.column,
+                error,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_violations() {
+        let input = b"a\x01\xe2\x82 \xed\xa0\x80\xc2\x85";
+        let found: Vec<_> = violations(input, Profile::Assignables)
+            .map(|e| (e.offset, e.len, e.violation))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                (1, 1, Violation::C0Control),
+                (2, 2, Violation::Utf8Truncated),
+                (5, 1, Violation::Utf8EncodedSurrogate),
+                (6, 1, Violation::InvalidUtf8),
+                (7, 1, Violation::InvalidUtf8),
+                (8, 2, Violation::C1Control),
+            ]
+        );
+        assert_eq!(violations(b"clean", Profile::Assignables).next(), None);
+        assert_eq!(violations(b"\xc3", Profile::Scalars).count(), 1);
+    }
+
+    #[test]
+    fn test_is_binary() {
+        assert!(is_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"));
+        assert!(!is_binary(b"caf\xe9\r\n"));
+        assert!(!is_binary(b""));
+        let mut late = vec![b'a'; BINARY_PROBE];
+        late.push(0);
+        assert!(!is_binary(&late));
+    }
+
+    #[test]
+    fn test_scan() {
+        let input = "caf\u{e9}\u{7f}\r\n\n\t\u{1f600}\u{85}x\u{fdd0}\n\u{b}";
+        let mut bytes = input.as_bytes().to_vec();
+        let surrogate = input.find('x').unwrap() + 1;
+        bytes.splice(surrogate..surrogate, *b"\xed\xa0\x80");
+
+        let found: Vec<String> = scan(&bytes, Profile::Assignables)
+            .iter()
+            .map(Finding::to_string)
+            .collect();
+        assert_eq!(
+            found,
+            [
+                "1:5: U+007F DEL control character",
+                "3:3: U+0085 legacy C1 control character",
+                "3:5: UTF-8 encoded surrogate",
+                "3:6: invalid UTF-8",
+                "3:7: invalid UTF-8",
+                "3:8: U+FDD0 noncharacter",
+                "4:1: U+000B legacy C0 control character",
+            ]
+        );
+        assert_eq!(scan(&bytes, Profile::Scalars).len(), 3);
+    }
+}
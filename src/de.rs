@@ -0,0 +1,726 @@
+//! serde [`Deserializer`] validating every string of a payload
+
+use core::fmt;
+use core::marker::PhantomData;
+
+use alloc::string::String;
+
+use serde::de::{
+    self, DeserializeSeed, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+
+use crate::{Rfc9839Subset, ValidationError};
+
+/// Location of a value within the payload being deserialized
+enum Path<'a> {
+    Root,
+    Seq {
+        parent: &'a Path<'a>,
+        index: usize,
+    },
+    /// A map value or the content of an enum variant
+    Map {
+        parent: &'a Path<'a>,
+        key: &'a dyn fmt::Display,
+    },
+}
+
+/// Map key or enum variant a value is found under, only copied when it
+/// can't be borrowed from the payload
+enum Key<'de> {
+    Unknown,
+    Borrowed(&'de str),
+    Owned(String),
+    Bool(bool),
+    Char(char),
+    Int(i128),
+    Uint(u128),
+    Float(f64),
+}
+
+impl fmt::Display for Key<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Key::Unknown => f.write_str("?"),
+            Key::Borrowed(s) => f.write_str(s),
+            Key::Owned(s) => f.write_str(s),
+            Key::Bool(b) => write!(f, "{}", b),
+            Key::Char(c) => write!(f, "{}", c),
+            Key::Int(i) => write!(f, "{}", i),
+            Key::Uint(u) => write!(f, "{}", u),
+            Key::Float(x) => write!(f, "{}", x),
+        }
+    }
+}
+
+impl fmt::Display for Path<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Path::Root => f.write_str("."),
+            Path::Seq {
+                parent: Path::Root,
+                index,
+            } => write!(f, "[{}]", index),
+            Path::Seq { parent, index } => write!(f, "{}[{}]", parent, index),
+            Path::Map {
+                parent: Path::Root,
+                key,
+            } => write!(f, "{}", key),
+            Path::Map { parent, key } => write!(f, "{}.{}", parent, key),
+        }
+    }
+}
+
+fn invalid<E: de::Error, S: Rfc9839Subset>(path: &Path<'_>, e: ValidationError) -> E {
+    E::custom(format_args!(
+        "invalid {} string at {}: {}",
+        S::NAME,
+        path,
+        e
+    ))
+}
+
+/// Deserializer checking every string, map key and `char` produced by `D`
+/// against the subset `S`.
+///
+/// Errors report the path of the first offending value within the payload.
+///
+/// ```
+/// use rfc9839_rs::{UnicodeAssignables, ValidatingDeserializer};
+///
+/// #[derive(serde::Deserialize)]
+/// struct Payload {
+///     tags: Vec<String>,
+/// }
+///
+/// let mut json = serde_json::Deserializer::from_str(r#"{"tags": ["ok", "\u0085"]}"#);
+/// let de = ValidatingDeserializer::<_, UnicodeAssignables>::new(&mut json);
+/// let err = serde::Deserialize::deserialize(de).map(|_: Payload| ()).unwrap_err();
+/// assert!(err.to_string().starts_with("invalid assignables string at tags[1]"));
+/// ```
+pub struct ValidatingDeserializer<D, S> {
+    inner: D,
+    _subset: PhantomData<fn() -> S>,
+}
+
+impl<D, S> ValidatingDeserializer<D, S> {
+    pub const fn new(inner: D) -> Self {
+        Self {
+            inner,
+            _subset: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> D {
+        self.inner
+    }
+}
+
+/// Deserializes a `T` from `deserializer`, checking every string against
+/// the subset `S`
+pub fn deserialize<'de, D, S, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    S: Rfc9839Subset,
+    T: de::Deserialize<'de>,
+{
+    T::deserialize(ValidatingDeserializer::<D, S>::new(deserializer))
+}
+
+/// Deserializer wrapper aware of the path of the value it produces
+struct Wrap<'p, 'k, 'de, D, S> {
+    inner: D,
+    path: &'p Path<'p>,
+    /// Set when deserializing a map key or an enum variant, to remember it for
+    /// the path of the value
+    key: Option<&'k mut Key<'de>>,
+    _subset: PhantomData<fn() -> S>,
+}
+
+impl<'p, 'k, 'de, D, S> Wrap<'p, 'k, 'de, D, S> {
+    fn new(inner: D, path: &'p Path<'p>, key: Option<&'k mut Key<'de>>) -> Self {
+        Self {
+            inner,
+            path,
+            key,
+            _subset: PhantomData,
+        }
+    }
+}
+
+/// Forwards every `deserialize_*` hint of a `Deserializer` to `self.inner`
+/// with a wrapped visitor, built by `$wrap`
+macro_rules! forward_deserialize {
+    ($wrap:ident) => {
+        forward_deserialize!($wrap;
+            deserialize_any, deserialize_bool, deserialize_i8, deserialize_i16,
+            deserialize_i32, deserialize_i64, deserialize_i128, deserialize_u8,
+            deserialize_u16, deserialize_u32, deserialize_u64, deserialize_u128,
+            deserialize_f32, deserialize_f64, deserialize_char, deserialize_str,
+            deserialize_string, deserialize_bytes, deserialize_byte_buf,
+            deserialize_option, deserialize_unit, deserialize_seq, deserialize_map,
+            deserialize_identifier, deserialize_ignored_any
+        );
+
+        fn deserialize_unit_struct<V: Visitor<'de>>(
+            self,
+            name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            let (inner, visitor) = $wrap!(self, visitor);
+            inner.deserialize_unit_struct(name, visitor)
+        }
+
+        fn deserialize_newtype_struct<V: Visitor<'de>>(
+            self,
+            name: &'static str,
+            visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            let (inner, visitor) = $wrap!(self, visitor);
+            inner.deserialize_newtype_struct(name, visitor)
+        }
+
+        fn deserialize_tuple<V: Visitor<'de>>(
+            self,
+            len: usize,
+            visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            let (inner, visitor) = $wrap!(self, visitor);
+            inner.deserialize_tuple(len, visitor)
+        }
+
+        fn deserialize_tuple_struct<V: Visitor<'de>>(
+            self,
+            name: &'static str,
+            len: usize,
+            visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            let (inner, visitor) = $wrap!(self, visitor);
+            inner.deserialize_tuple_struct(name, len, visitor)
+        }
+
+        fn deserialize_struct<V: Visitor<'de>>(
+            self,
+            name: &'static str,
+            fields: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            let (inner, visitor) = $wrap!(self, visitor);
+            inner.deserialize_struct(name, fields, visitor)
+        }
+
+        fn deserialize_enum<V: Visitor<'de>>(
+            self,
+            name: &'static str,
+            variants: &'static [&'static str],
+            visitor: V,
+        ) -> Result<V::Value, Self::Error> {
+            let (inner, visitor) = $wrap!(self, visitor);
+            inner.deserialize_enum(name, variants, visitor)
+        }
+
+        fn is_human_readable(&self) -> bool {
+            self.inner.is_human_readable()
+        }
+    };
+    ($wrap:ident; $($method:ident),*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+                let (inner, visitor) = $wrap!(self, visitor);
+                inner.$method(visitor)
+            }
+        )*
+    };
+}
+
+macro_rules! wrap_root {
+    ($self:ident, $visitor:ident) => {
+        (
+            $self.inner,
+            WrapVisitor::<V, S> {
+                inner: $visitor,
+                path: &Path::Root,
+                key: None,
+                _subset: PhantomData,
+            },
+        )
+    };
+}
+
+macro_rules! wrap_nested {
+    ($self:ident, $visitor:ident) => {
+        (
+            $self.inner,
+            WrapVisitor::<V, S> {
+                inner: $visitor,
+                path: $self.path,
+                key: $self.key,
+                _subset: PhantomData,
+            },
+        )
+    };
+}
+
+impl<'de, D: Deserializer<'de>, S: Rfc9839Subset> Deserializer<'de>
+    for ValidatingDeserializer<D, S>
+{
+    type Error = D::Error;
+
+    forward_deserialize!(wrap_root);
+}
+
+impl<'de, D: Deserializer<'de>, S: Rfc9839Subset> Deserializer<'de> for Wrap<'_, '_, 'de, D, S> {
+    type Error = D::Error;
+
+    forward_deserialize!(wrap_nested);
+}
+
+struct WrapVisitor<'p, 'k, 'de, V, S> {
+    inner: V,
+    path: &'p Path<'p>,
+    key: Option<&'k mut Key<'de>>,
+    _subset: PhantomData<fn() -> S>,
+}
+
+impl<'de, V, S: Rfc9839Subset> WrapVisitor<'_, '_, 'de, V, S> {
+    fn check<E: de::Error>(&self, v: &str) -> Result<(), E> {
+        S::validate_str(v).map_err(|e| invalid::<E, S>(self.path, e))
+    }
+
+    /// Remembers the value when it is a map key or an enum variant, only
+    /// building it then
+    fn remember(&mut self, key: impl FnOnce() -> Key<'de>) {
+        if let Some(slot) = self.key.as_mut() {
+            **slot = key();
+        }
+    }
+}
+
+/// Forwards `visit_*` methods taking a primitive, remembering the value as a
+/// [`Key`] when it is one
+macro_rules! forward_visit {
+    ($($method:ident: $ty:ty => $key:ident),*) => {
+        $(
+            #[allow(clippy::useless_conversion)]
+            fn $method<E: de::Error>(mut self, v: $ty) -> Result<Self::Value, E> {
+                self.remember(|| Key::$key(v.into()));
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+impl<'de, V: Visitor<'de>, S: Rfc9839Subset> Visitor<'de> for WrapVisitor<'_, '_, 'de, V, S> {
+    type Value = V::Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.expecting(f)
+    }
+
+    forward_visit!(
+        visit_bool: bool => Bool, visit_i8: i8 => Int, visit_i16: i16 => Int,
+        visit_i32: i32 => Int, visit_i64: i64 => Int, visit_i128: i128 => Int,
+        visit_u8: u8 => Uint, visit_u16: u16 => Uint, visit_u32: u32 => Uint,
+        visit_u64: u64 => Uint, visit_u128: u128 => Uint, visit_f32: f32 => Float,
+        visit_f64: f64 => Float
+    );
+
+    fn visit_char<E: de::Error>(mut self, v: char) -> Result<Self::Value, E> {
+        if let Err(violation) = S::classify(v as u32) {
+            let e = ValidationError::new(0, Some(v as u32), violation);
+            return Err(invalid::<E, S>(self.path, e));
+        }
+        self.remember(|| Key::Char(v));
+        self.inner.visit_char(v)
+    }
+
+    fn visit_str<E: de::Error>(mut self, v: &str) -> Result<Self::Value, E> {
+        self.check(v)?;
+        self.remember(|| Key::Owned(String::from(v)));
+        self.inner.visit_str(v)
+    }
+
+    fn visit_borrowed_str<E: de::Error>(mut self, v: &'de str) -> Result<Self::Value, E> {
+        self.check(v)?;
+        self.remember(|| Key::Borrowed(v));
+        self.inner.visit_borrowed_str(v)
+    }
+
+    fn visit_string<E: de::Error>(mut self, v: String) -> Result<Self::Value, E> {
+        self.check(&v)?;
+        self.remember(|| Key::Owned(v.clone()));
+        self.inner.visit_string(v)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        self.inner.visit_bytes(v)
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Self::Value, E> {
+        self.inner.visit_borrowed_bytes(v)
+    }
+
+    fn visit_byte_buf<E: de::Error>(self, v: alloc::vec::Vec<u8>) -> Result<Self::Value, E> {
+        self.inner.visit_byte_buf(v)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_none()
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        self.inner
+            .visit_some(Wrap::<D, S>::new(deserializer, self.path, None))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        self.inner.visit_unit()
+    }
+
+    fn visit_newtype_struct<D: Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        self.inner
+            .visit_newtype_struct(Wrap::<D, S>::new(deserializer, self.path, None))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, seq: A) -> Result<Self::Value, A::Error> {
+        self.inner.visit_seq(WrapSeq::<A, S> {
+            inner: seq,
+            path: self.path,
+            index: 0,
+            _subset: PhantomData,
+        })
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+        self.inner.visit_map(WrapMap::<A, S> {
+            inner: map,
+            path: self.path,
+            key: Key::Unknown,
+            _subset: PhantomData,
+        })
+    }
+
+    fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+        self.inner.visit_enum(WrapEnum::<A, S> {
+            inner: data,
+            path: self.path,
+            _subset: PhantomData,
+        })
+    }
+}
+
+/// Seed deserializing its value through a [`Wrap`]
+struct WrapSeed<'p, 'k, 'de, T, S> {
+    inner: T,
+    path: &'p Path<'p>,
+    key: Option<&'k mut Key<'de>>,
+    _subset: PhantomData<fn() -> S>,
+}
+
+impl<'p, 'k, 'de, T, S> WrapSeed<'p, 'k, 'de, T, S> {
+    fn new(inner: T, path: &'p Path<'p>, key: Option<&'k mut Key<'de>>) -> Self {
+        Self {
+            inner,
+            path,
+            key,
+            _subset: PhantomData,
+        }
+    }
+}
+
+impl<'de, T: DeserializeSeed<'de>, S: Rfc9839Subset> DeserializeSeed<'de>
+    for WrapSeed<'_, '_, 'de, T, S>
+{
+    type Value = T::Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<T::Value, D::Error> {
+        self.inner
+            .deserialize(Wrap::<D, S>::new(deserializer, self.path, self.key))
+    }
+}
+
+struct WrapSeq<'p, A, S> {
+    inner: A,
+    path: &'p Path<'p>,
+    index: usize,
+    _subset: PhantomData<fn() -> S>,
+}
+
+impl<'de, A: SeqAccess<'de>, S: Rfc9839Subset> SeqAccess<'de> for WrapSeq<'_, A, S> {
+    type Error = A::Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, A::Error> {
+        let path = Path::Seq {
+            parent: self.path,
+            index: self.index,
+        };
+        self.index += 1;
+        self.inner
+            .next_element_seed(WrapSeed::<T, S>::new(seed, &path, None))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+struct WrapMap<'p, 'de, A, S> {
+    inner: A,
+    path: &'p Path<'p>,
+    /// Last key, once deserialized
+    key: Key<'de>,
+    _subset: PhantomData<fn() -> S>,
+}
+
+impl<'de, A: MapAccess<'de>, S: Rfc9839Subset> MapAccess<'de> for WrapMap<'_, 'de, A, S> {
+    type Error = A::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, A::Error> {
+        self.key = Key::Unknown;
+        self.inner
+            .next_key_seed(WrapSeed::<K, S>::new(seed, self.path, Some(&mut self.key)))
+    }
+
+    fn next_value_seed<T: DeserializeSeed<'de>>(&mut self, seed: T) -> Result<T::Value, A::Error> {
+        let path = Path::Map {
+            parent: self.path,
+            key: &self.key,
+        };
+        self.inner
+            .next_value_seed(WrapSeed::<T, S>::new(seed, &path, None))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        self.inner.size_hint()
+    }
+}
+
+struct WrapEnum<'p, A, S> {
+    inner: A,
+    path: &'p Path<'p>,
+    _subset: PhantomData<fn() -> S>,
+}
+
+impl<'p, 'de, A: EnumAccess<'de>, S: Rfc9839Subset> EnumAccess<'de> for WrapEnum<'p, A, S> {
+    type Error = A::Error;
+    type Variant = WrapVariant<'p, 'de, A::Variant, S>;
+
+    fn variant_seed<T: DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<(T::Value, Self::Variant), A::Error> {
+        let mut key = Key::Unknown;
+        let (value, variant) =
+            self.inner
+                .variant_seed(WrapSeed::<T, S>::new(seed, self.path, Some(&mut key)))?;
+        Ok((
+            value,
+            WrapVariant {
+                inner: variant,
+                parent: self.path,
+                key,
+                _subset: PhantomData,
+            },
+        ))
+    }
+}
+
+struct WrapVariant<'p, 'de, A, S> {
+    inner: A,
+    parent: &'p Path<'p>,
+    /// Name or index of the variant
+    key: Key<'de>,
+    _subset: PhantomData<fn() -> S>,
+}
+
+impl<'de, A: VariantAccess<'de>, S: Rfc9839Subset> VariantAccess<'de>
+    for WrapVariant<'_, 'de, A, S>
+{
+    type Error = A::Error;
+
+    fn unit_variant(self) -> Result<(), A::Error> {
+        self.inner.unit_variant()
+    }
+
+    fn newtype_variant_seed<T: DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value, A::Error> {
+        let path = Path::Map {
+            parent: self.parent,
+            key: &self.key,
+        };
+        self.inner
+            .newtype_variant_seed(WrapSeed::<T, S>::new(seed, &path, None))
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, A::Error> {
+        let path = Path::Map {
+            parent: self.parent,
+            key: &self.key,
+        };
+        self.inner.tuple_variant(
+            len,
+            WrapVisitor::<V, S> {
+                inner: visitor,
+                path: &path,
+                key: None,
+                _subset: PhantomData,
+            },
+        )
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, A::Error> {
+        let path = Path::Map {
+            parent: self.parent,
+            key: &self.key,
+        };
+        self.inner.struct_variant(
+            fields,
+            WrapVisitor::<V, S> {
+                inner: visitor,
+                path: &path,
+                key: None,
+                _subset: PhantomData,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::{UnicodeAssignables, XmlCharacters};
+
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    enum Shape {
+        Named(String),
+        Tagged { tag: char },
+    }
+
+    #[derive(Debug, serde::Deserialize)]
+    #[allow(dead_code)]
+    struct Model {
+        name: String,
+        attributes: BTreeMap<String, Vec<String>>,
+        shapes: Vec<Shape>,
+        note: Option<String>,
+    }
+
+    fn parse<S: Rfc9839Subset>(json: &str) -> Result<Model, String> {
+        let mut de = serde_json::Deserializer::from_str(json);
+        deserialize::<_, S, Model>(&mut de).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_paths() {
+        let ok = r#"{"name": "a", "attributes": {"k": ["v"]}, "shapes": [], "note": null}"#;
+        parse::<UnicodeAssignables>(ok).unwrap();
+
+        let bad = r#"{"name": "\u007f", "attributes": {}, "shapes": [], "note": null}"#;
+        let err = parse::<UnicodeAssignables>(bad).unwrap_err();
+        assert!(
+            err.starts_with("invalid assignables string at name: DEL"),
+            "{}",
+            err
+        );
+        parse::<XmlCharacters>(bad).unwrap();
+
+        let bad =
+            r#"{"name": "", "attributes": {"k": ["v", "\u0001"]}, "shapes": [], "note": null}"#;
+        let err = parse::<XmlCharacters>(bad).unwrap_err();
+        assert!(
+            err.starts_with("invalid xml string at attributes.k[1]:"),
+            "{}",
+            err
+        );
+
+        let bad = r#"{"name": "", "attributes": {"\u0002": []}, "shapes": [], "note": null}"#;
+        let err = parse::<XmlCharacters>(bad).unwrap_err();
+        assert!(
+            err.starts_with("invalid xml string at attributes:"),
+            "{}",
+            err
+        );
+
+        let bad = r#"{"name": "", "attributes": {}, "shapes": [{"Named": "ok"}, {"Tagged": {"tag": "￾"}}], "note": null}"#;
+        let err = parse::<UnicodeAssignables>(bad).unwrap_err();
+        assert!(
+            err.starts_with("invalid assignables string at shapes[1].Tagged.tag:"),
+            "{}",
+            err
+        );
+
+        let bad =
+            r#"{"name": "", "attributes": {}, "shapes": [{"Named": "\u0085"}], "note": null}"#;
+        let err = parse::<UnicodeAssignables>(bad).unwrap_err();
+        assert!(
+            err.starts_with("invalid assignables string at shapes[0].Named:"),
+            "{}",
+            err
+        );
+
+        let bad = r#"{"name": "", "attributes": {}, "shapes": [], "note": "\u009b"}"#;
+        let err = parse::<UnicodeAssignables>(bad).unwrap_err();
+        assert!(
+            err.starts_with("invalid assignables string at note: legacy C1"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_chars_and_keys() {
+        use serde::de::IntoDeserializer;
+        use serde::de::value::{Error, MapDeserializer};
+
+        // `visit_char`, which serde_json never calls
+        let de = IntoDeserializer::<Error>::into_deserializer('\u{85}');
+        let err = deserialize::<_, UnicodeAssignables, char>(de).unwrap_err();
+        assert!(
+            err.to_string()
+                .starts_with("invalid assignables string at .: legacy C1"),
+            "{}",
+            err
+        );
+        let de = IntoDeserializer::<Error>::into_deserializer('\u{85}');
+        assert_eq!(deserialize::<_, XmlCharacters, char>(de), Ok('\u{85}'));
+
+        // keys which aren't strings
+        let map = MapDeserializer::<_, Error>::new([('k', "ok"), ('\u{1b}', "")].into_iter());
+        let err = deserialize::<_, XmlCharacters, BTreeMap<char, String>>(map).unwrap_err();
+        assert!(
+            err.to_string().starts_with("invalid xml string at .:"),
+            "{}",
+            err
+        );
+        let map = MapDeserializer::<_, Error>::new([('k', "ok"), ('j', "\u{1b}")].into_iter());
+        let err = deserialize::<_, XmlCharacters, BTreeMap<char, String>>(map).unwrap_err();
+        assert!(
+            err.to_string().starts_with("invalid xml string at j:"),
+            "{}",
+            err
+        );
+        let map = MapDeserializer::<_, Error>::new([(7u32, "\u{fffe}")].into_iter());
+        let err = deserialize::<_, XmlCharacters, BTreeMap<u32, String>>(map).unwrap_err();
+        assert!(
+            err.to_string().starts_with("invalid xml string at 7:"),
+            "{}",
+            err
+        );
+    }
+}
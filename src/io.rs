@@ -0,0 +1,336 @@
+//! [`std::io`] adapters enforcing a subset on the data going through them
+
+use std::io::{self, Read, Write};
+
+use crate::{Rfc9839Subset, StreamValidator, ValidationError};
+
+fn into_io_error(e: ValidationError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Reader validating everything read from `R` against the subset `S`.
+///
+/// Data is handed out up to the first violation, the next read then fails
+/// with an [`io::ErrorKind::InvalidData`] error wrapping the
+/// [`ValidationError`]. The bytes of a character are only handed out once all
+/// of them were read and validated, a sequence split across reads of `R` is
+/// held back in the meantime.
+///
+/// ```
+/// use std::io::Read;
+/// use rfc9839_rs::{UnicodeAssignables, ValidatingReader, ValidationError};
+///
+/// let mut reader = ValidatingReader::<_, UnicodeAssignables>::new(&b"ok\x7f"[..]);
+/// let mut out = String::new();
+/// let err = reader.read_to_string(&mut out).unwrap_err();
+/// let err = err.get_ref().unwrap().downcast_ref::<ValidationError>().unwrap();
+/// assert_eq!(err.offset(), 2);
+/// ```
+#[derive(Debug)]
+pub struct ValidatingReader<R, S> {
+    inner: R,
+    validator: StreamValidator<S>,
+    /// Bytes read from `R` but not handed out yet
+    held: [u8; 4],
+    held_len: usize,
+    /// Number of the held bytes which belong to a validated character, the
+    /// rest being an incomplete sequence
+    ready: usize,
+}
+
+impl<R, S> ValidatingReader<R, S> {
+    pub const fn new(inner: R) -> Self {
+        Self {
+            inner,
+            validator: StreamValidator::new(),
+            held: [0; 4],
+            held_len: 0,
+            ready: 0,
+        }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read, S: Rfc9839Subset> ValidatingReader<R, S> {
+    /// Hands out the held bytes of a validated character
+    fn read_ready(&mut self, buf: &mut [u8]) -> usize {
+        let n = self.ready.min(buf.len());
+        buf[..n].copy_from_slice(&self.held[..n]);
+        self.held.copy_within(n..self.held_len, 0);
+        self.held_len -= n;
+        self.ready -= n;
+        n
+    }
+}
+
+impl<R: Read, S: Rfc9839Subset> Read for ValidatingReader<R, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // feeding nothing reports a violation found by a previous read
+        self.validator.feed(&[]).map_err(into_io_error)?;
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        loop {
+            if self.ready > 0 {
+                return Ok(self.read_ready(buf));
+            }
+            let held = self.held_len;
+            if buf.len() <= held {
+                // too small for the held bytes along with the next one, the
+                // sequence is completed in `held` one byte at a time
+                let n = self.inner.read(&mut self.held[held..held + 1])?;
+                if n == 0 {
+                    self.validator.finish().map_err(into_io_error)?;
+                    return Ok(0);
+                }
+                self.validator
+                    .feed(&self.held[held..held + 1])
+                    .map_err(into_io_error)?;
+                self.held_len += 1;
+                if self.validator.pending() == 0 {
+                    self.ready = self.held_len;
+                }
+                continue;
+            }
+            buf[..held].copy_from_slice(&self.held[..held]);
+            let n = self.inner.read(&mut buf[held..])?;
+            if n == 0 {
+                self.validator.finish().map_err(into_io_error)?;
+                return Ok(0);
+            }
+            // offset of `buf[0]` in the stream
+            let start = self.validator.offset() - held;
+            self.held_len = 0;
+            if let Err(e) = self.validator.feed(&buf[held..held + n]) {
+                return match e.offset() - start {
+                    0 => Err(into_io_error(e)),
+                    valid => Ok(valid),
+                };
+            }
+            let complete = held + n - self.validator.pending();
+            self.held_len = held + n - complete;
+            self.held[..self.held_len].copy_from_slice(&buf[complete..held + n]);
+            if complete > 0 {
+                return Ok(complete);
+            }
+        }
+    }
+}
+
+/// Writer validating everything written to `W` against the subset `S`.
+///
+/// Writes containing a violation fail with an
+/// [`io::ErrorKind::InvalidData`] error wrapping the [`ValidationError`], and
+/// nothing from them reaches `W`. Only the bytes `W` accepts are taken into
+/// account by the validation, as with partial writes.
+#[derive(Debug)]
+pub struct ValidatingWriter<W, S> {
+    inner: W,
+    validator: StreamValidator<S>,
+}
+
+impl<W, S> ValidatingWriter<W, S> {
+    pub const fn new(inner: W) -> Self {
+        Self {
+            inner,
+            validator: StreamValidator::new(),
+        }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+impl<W: Write, S> ValidatingWriter<W, S> {
+    /// Checks that the data didn't end in the middle of a sequence, flushes
+    /// and returns the inner writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.validator.finish().map_err(into_io_error)?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write, S: Rfc9839Subset> Write for ValidatingWriter<W, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut checked = self.validator.clone();
+        if let Err(e) = checked.feed(buf) {
+            self.validator = checked;
+            return Err(into_io_error(e));
+        }
+        let n = self.inner.write(buf)?;
+        if n == buf.len() {
+            self.validator = checked;
+        } else {
+            // a prefix of valid data, at worst stopping inside a sequence
+            let _ = self.validator.feed(&buf[..n]);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{UnicodeAssignables, Violation, XmlCharacters};
+
+    /// Reader handing out one byte at a time
+    struct Trickle<'a>(&'a [u8]);
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.0.len().min(buf.len()).min(1);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    fn validation_error(e: &io::Error) -> ValidationError {
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        *e.get_ref().unwrap().downcast_ref::<ValidationError>().unwrap()
+    }
+
+    #[test]
+    fn test_reader() {
+        let input = "h\u{e9}llo \u{1f600}\u{85}".as_bytes();
+        let mut reader = ValidatingReader::<_, XmlCharacters>::new(Trickle(input));
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).unwrap();
+        assert_eq!(out, input);
+
+        let mut reader = ValidatingReader::<_, UnicodeAssignables>::new(input);
+        let mut out = Vec::new();
+        let err = reader.read_to_end(&mut out).unwrap_err();
+        assert_eq!(validation_error(&err).offset(), 11);
+        assert_eq!(out, &input[..11]);
+
+        let mut reader = ValidatingReader::<_, UnicodeAssignables>::new(Trickle(b"ab\xf0\x9f"));
+        let err = reader.read_to_end(&mut Vec::new()).unwrap_err();
+        assert_eq!(validation_error(&err).violation(), Violation::Utf8Truncated);
+    }
+
+    #[test]
+    fn test_reader_holds_back_sequences() {
+        let mut reader = ValidatingReader::<_, UnicodeAssignables>::new(Trickle(b"ab\xc2\x85cd"));
+        let mut out = Vec::new();
+        let err = reader.read_to_end(&mut out).unwrap_err();
+        assert_eq!(validation_error(&err).offset(), 2);
+        assert_eq!(out, b"ab");
+
+        // the bytes of a character are handed out once it was validated
+        let input = "\u{e9}\u{1f600}a\u{4e2d}".as_bytes();
+        let mut reader = ValidatingReader::<_, UnicodeAssignables>::new(Trickle(input));
+        let mut buf = [0; 8];
+        let mut reads = Vec::new();
+        loop {
+            match reader.read(&mut buf).unwrap() {
+                0 => break,
+                n => reads.push(buf[..n].to_vec()),
+            }
+        }
+        assert_eq!(
+            reads,
+            ["\u{e9}", "\u{1f600}", "a", "\u{4e2d}"].map(|s| s.as_bytes().to_vec())
+        );
+
+        // buffers smaller than a character
+        for len in 1..4 {
+            let mut reader = ValidatingReader::<_, XmlCharacters>::new(Trickle(input));
+            let mut buf = [0; 3];
+            let mut out = Vec::new();
+            loop {
+                match reader.read(&mut buf[..len]).unwrap() {
+                    0 => break,
+                    n => out.extend_from_slice(&buf[..n]),
+                }
+            }
+            assert_eq!(out, input);
+        }
+        let mut reader =
+            ValidatingReader::<_, XmlCharacters>::new(Trickle(b"\xf0\x9f\x98\x80\xef\xbf\xbe"));
+        let mut buf = [0; 2];
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        assert_eq!(reader.read(&mut buf).unwrap(), 2);
+        let err = reader.read(&mut buf).unwrap_err();
+        assert_eq!(validation_error(&err).offset(), 4);
+    }
+
+    #[test]
+    fn test_writer() {
+        let mut writer = ValidatingWriter::<_, UnicodeAssignables>::new(Vec::new());
+        writer.write_all(b"caf\xc3").unwrap();
+        writer.write_all(b"\xa9\n").unwrap();
+        let err = writer.write_all(b"bad\x1b").unwrap_err();
+        assert_eq!(validation_error(&err).offset(), 9);
+        assert_eq!(writer.get_ref(), "café\n".as_bytes());
+
+        let mut writer = ValidatingWriter::<_, UnicodeAssignables>::new(Vec::new());
+        writer.write_all(b"\xc3").unwrap();
+        let err = writer.finish().unwrap_err();
+        assert_eq!(validation_error(&err).violation(), Violation::Utf8Truncated);
+    }
+
+    /// Writer accepting one byte at a time, and failing once `fail` is set
+    #[derive(Debug)]
+    struct Drip {
+        out: Vec<u8>,
+        fail: bool,
+    }
+
+    impl Write for Drip {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.fail {
+                return Err(io::ErrorKind::Interrupted.into());
+            }
+            let n = buf.len().min(1);
+            self.out.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_writer_partial_writes() {
+        let drip = Drip {
+            out: Vec::new(),
+            fail: false,
+        };
+        let mut writer = ValidatingWriter::<_, UnicodeAssignables>::new(drip);
+        assert_eq!(writer.write("\u{e9}t\u{e9}".as_bytes()).unwrap(), 1);
+        writer.get_mut().fail = true;
+        assert!(writer.write(b"\xa9").is_err());
+        writer.get_mut().fail = false;
+        // only what reached the inner writer was validated
+        writer.write_all(b"\xa9t\xc3\xa9").unwrap();
+        let err = writer.write(b"\x7f").unwrap_err();
+        assert_eq!(validation_error(&err).offset(), 5);
+        assert_eq!(
+            writer.finish().unwrap_err().kind(),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
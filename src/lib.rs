@@ -4,9 +4,63 @@
 //!
 //! A rust implementation of RFC9839 to test for problematic Unicode code points
 
+#[cfg(feature = "alloc")]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+#[cfg(all(feature = "serde", feature = "alloc"))]
+mod de;
+#[cfg(feature = "std")]
+mod io;
+#[cfg(feature = "rayon")]
+mod par;
+mod profile;
+mod regex;
+mod sanitize;
+#[cfg(feature = "schemars")]
+mod schema;
+#[cfg(all(feature = "serde", feature = "alloc"))]
+mod ser;
+#[cfg(feature = "serde")]
+mod serde_impl;
+mod set;
+mod simd;
+mod stream;
+mod string;
+mod subset;
+mod swar;
+mod utf8;
+mod validate;
+mod violation;
+
+#[cfg(all(feature = "serde", feature = "alloc"))]
+pub use de::{deserialize, ValidatingDeserializer};
+#[cfg(feature = "std")]
+pub use io::{ValidatingReader, ValidatingWriter};
+#[cfg(feature = "rayon")]
+pub use par::{par_validate_utf8, par_validate_utf8_all};
+pub use profile::{ParseProfileError, Profile};
+pub use regex::{RegexClass, RegexDialect};
+pub use sanitize::Replacement;
+pub use set::{CodePointSet, Members, NonMembers};
+#[cfg(all(feature = "serde", feature = "alloc"))]
+pub use ser::{serialize, SerializeMode, ValidatingCompound, ValidatingSerializer};
+#[cfg(all(feature = "serde", feature = "alloc"))]
+pub use serde_impl::{assignables, xml};
+pub use stream::StreamValidator;
+pub use string::{AssignableStr, ValidStr, XmlStr};
+#[cfg(feature = "alloc")]
+pub use string::{AssignableString, FromStringError, ValidString, XmlString};
+pub use subset::Rfc9839Subset;
+pub use validate::{ByteOrder, ValidationError};
+pub use violation::Violation;
+
+use core::ops::RangeInclusive;
 
 /// Check if the value is either a low or high surrogate
 /// these characters should not be encoded as part of a UTF-8 stream.
+#[allow(clippy::manual_range_patterns)]
 pub const fn is_unicode_surrotate(c: u32) -> bool {
     matches!(c, 0xd800..=0xdbff | 0xdc00..=0xdfff)
 }
@@ -88,8 +142,39 @@ pub const fn is_noncharacter(c: u32) -> bool {
 pub struct UnicodeScalars {}
 
 impl UnicodeScalars {
+    /// Sorted, non overlapping inclusive ranges of the code points in the
+    /// subset, the same data as [`contains`](Self::contains)
+    pub const RANGES: &'static [RangeInclusive<u32>] = &[0x0..=0xd7ff, 0xe000..=0x10ffff];
+
+    /// The subset as a set of code points
+    pub const SET: CodePointSet<2> = CodePointSet::from_ranges(Self::RANGES);
+
     pub const fn contains(c: u32) -> bool {
-        !is_unicode_surrotate(c)
+        c <= 0x10ffff
+        && !is_unicode_surrotate(c)
+    }
+
+    /// Explains why `c` isn't a Unicode scalar
+    pub const fn classify(c: u32) -> Result<(), Violation> {
+        if c > 0x10ffff {
+            Err(Violation::OutOfRange)
+        } else if is_unicode_surrotate(c) {
+            Err(Violation::Surrogate)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks that every character of `s` is a Unicode scalar, returning the
+    /// first one that isn't
+    pub fn validate_str(s: &str) -> Result<(), ValidationError> {
+        validate::validate_str(s, Self::classify, Self::FAST_PATHS.utf8_text)
+    }
+
+    /// Decodes `bytes` as UTF-8 and checks that every character is a Unicode
+    /// scalar, returning the first one that isn't
+    pub fn validate_utf8(bytes: &[u8]) -> Result<(), ValidationError> {
+        validate::validate_utf8(bytes, Self::classify, Self::FAST_PATHS.utf8_text)
     }
 }
 
@@ -98,26 +183,131 @@ impl UnicodeScalars {
 pub struct XmlCharacters {}
 
 impl XmlCharacters {
+    /// Sorted, non overlapping inclusive ranges of the code points in the
+    /// subset, the same data as [`contains`](Self::contains)
+    pub const RANGES: &'static [RangeInclusive<u32>] = &[
+        0x9..=0xa,
+        0xd..=0xd,
+        0x20..=0xd7ff,
+        0xe000..=0xfffd,
+        0x10000..=0x10ffff,
+    ];
+
+    /// The subset as a set of code points
+    pub const SET: CodePointSet<5> = CodePointSet::from_ranges(Self::RANGES);
+
+    #[allow(clippy::nonminimal_bool)]
     pub const fn contains(c: u32) -> bool {
-        !(control::is_c0_control(c)
+        c <= 0x10ffff
+        && !(control::is_c0_control(c)
             && !control::is_useful_control(c))
         && !is_unicode_surrotate(c)
         && !matches!(c, 0xfffe..=0xffff)
     }
+
+    /// Explains why `c` isn't an XML character
+    pub const fn classify(c: u32) -> Result<(), Violation> {
+        if c > 0x10ffff {
+            Err(Violation::OutOfRange)
+        } else if control::is_c0_control(c) && !control::is_useful_control(c) {
+            Err(Violation::C0Control)
+        } else if is_unicode_surrotate(c) {
+            Err(Violation::Surrogate)
+        } else if matches!(c, 0xfffe..=0xffff) {
+            Err(Violation::Noncharacter)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks that every character of `s` is an XML character, returning the
+    /// first one that isn't
+    pub fn validate_str(s: &str) -> Result<(), ValidationError> {
+        validate::validate_str(s, Self::classify, Self::FAST_PATHS.utf8_text)
+    }
+
+    /// Decodes `bytes` as UTF-8 and checks that every character is an XML
+    /// character, returning the first one that isn't
+    pub fn validate_utf8(bytes: &[u8]) -> Result<(), ValidationError> {
+        validate::validate_utf8(bytes, Self::classify, Self::FAST_PATHS.utf8_text)
+    }
 }
 
 /// Unicode code points that are not problematic. As specified by RFC9839.
 pub struct UnicodeAssignables {}
 
 impl UnicodeAssignables {
+    /// Sorted, non overlapping inclusive ranges of the code points in the
+    /// subset, the same data as [`contains`](Self::contains)
+    pub const RANGES: &'static [RangeInclusive<u32>] = &[
+        0x9..=0xa,
+        0xd..=0xd,
+        0x20..=0x7e,
+        0xa0..=0xd7ff,
+        0xe000..=0xfdcf,
+        0xfdf0..=0xfffd,
+        0x10000..=0x1fffd,
+        0x20000..=0x2fffd,
+        0x30000..=0x3fffd,
+        0x40000..=0x4fffd,
+        0x50000..=0x5fffd,
+        0x60000..=0x6fffd,
+        0x70000..=0x7fffd,
+        0x80000..=0x8fffd,
+        0x90000..=0x9fffd,
+        0xa0000..=0xafffd,
+        0xb0000..=0xbfffd,
+        0xc0000..=0xcfffd,
+        0xd0000..=0xdfffd,
+        0xe0000..=0xefffd,
+        0xf0000..=0xffffd,
+        0x100000..=0x10fffd,
+    ];
+
+    /// The subset as a set of code points
+    pub const SET: CodePointSet<22> = CodePointSet::from_ranges(Self::RANGES);
+
+    #[allow(clippy::nonminimal_bool)]
     pub const fn contains(c: u32) -> bool {
-        c != 0x7f // del
+        c <= 0x10ffff
+        && c != 0x7f // del
         && !(control::is_c0_control(c)
             && !control::is_useful_control(c))
         && !control::is_c1_control(c)
         && !is_unicode_surrotate(c)
         && !is_noncharacter(c)
     }
+
+    /// Explains why `c` isn't a Unicode assignable
+    pub const fn classify(c: u32) -> Result<(), Violation> {
+        if c > 0x10ffff {
+            Err(Violation::OutOfRange)
+        } else if c == 0x7f {
+            Err(Violation::Delete)
+        } else if control::is_c0_control(c) && !control::is_useful_control(c) {
+            Err(Violation::C0Control)
+        } else if control::is_c1_control(c) {
+            Err(Violation::C1Control)
+        } else if is_unicode_surrotate(c) {
+            Err(Violation::Surrogate)
+        } else if is_noncharacter(c) {
+            Err(Violation::Noncharacter)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Checks that every character of `s` is a Unicode assignable, returning
+    /// the first one that isn't
+    pub fn validate_str(s: &str) -> Result<(), ValidationError> {
+        validate::validate_str(s, Self::classify, Self::FAST_PATHS.utf8_text)
+    }
+
+    /// Decodes `bytes` as UTF-8 and checks that every character is a Unicode
+    /// assignable, returning the first one that isn't
+    pub fn validate_utf8(bytes: &[u8]) -> Result<(), ValidationError> {
+        validate::validate_utf8(bytes, Self::classify, Self::FAST_PATHS.utf8_text)
+    }
 }
 
 #[cfg(test)]
@@ -126,6 +316,7 @@ mod test {
     use core::ops::RangeInclusive;
 
     #[track_caller]
+    #[allow(clippy::bool_comparison)]
     fn assert_predicate(p: fn(u32) -> bool, ranges: &[RangeInclusive<u32>]) {
         let mut last = 0;
         for range in ranges {
@@ -145,7 +336,7 @@ mod test {
                     u);
             }
         }
-        for i in last..=(char::MAX as u32) {
+        for i in last..=u32::MAX {
             assert!(
                 p(i) == false,
                 "{}: {:x} should not be included but is",
@@ -206,4 +397,85 @@ mod test {
         ];
         assert_predicate(UnicodeAssignables::contains, &ranges);
     }
+
+    /// `ranges`, `RANGES` and `SET` hold the ranges merged, without adjacent
+    /// ones
+    #[test]
+    fn test_ranges() {
+        let scalars = [0x0..=0xd7ff, 0xe000..=0x10ffff];
+        assert_eq!(<UnicodeScalars as Rfc9839Subset>::ranges(), &scalars);
+        assert_eq!(UnicodeScalars::RANGES, &scalars);
+        assert_eq!(UnicodeScalars::SET.as_ranges(), &scalars);
+
+        let xml = [
+            0x9..=0xa,
+            0xd..=0xd,
+            0x20..=0xd7ff,
+            0xe000..=0xfffd,
+            0x10000..=0x10ffff,
+        ];
+        assert_eq!(<XmlCharacters as Rfc9839Subset>::ranges(), &xml);
+        assert_eq!(XmlCharacters::RANGES, &xml);
+        assert_eq!(XmlCharacters::SET.as_ranges(), &xml);
+
+        let mut assignables = std::vec![
+            0x9..=0xa,
+            0xd..=0xd,
+            0x20..=0x7e,
+            0xa0..=0xd7ff,
+            0xe000..=0xfdcf,
+            0xfdf0..=0xfffd,
+        ];
+        assignables.extend((0x1..=0x10).map(|plane| plane << 16..=plane << 16 | 0xfffd));
+        assert_eq!(<UnicodeAssignables as Rfc9839Subset>::ranges(), assignables);
+        assert_eq!(UnicodeAssignables::RANGES, assignables);
+        assert_eq!(UnicodeAssignables::SET.as_ranges(), assignables);
+    }
+
+    #[test]
+    fn test_validate_str() {
+        assert_eq!(UnicodeAssignables::validate_str("hello\tworld\n"), Ok(()));
+        let err = UnicodeAssignables::validate_str("ab\u{85}c\u{7f}").unwrap_err();
+        assert_eq!(err.offset(), 2);
+        assert_eq!(err.code_point(), Some(0x85));
+        assert_eq!(err.violation(), Violation::C1Control);
+        assert_eq!(XmlCharacters::validate_str("ab\u{85}c"), Ok(()));
+        let err = XmlCharacters::validate_str("é\u{ffff}").unwrap_err();
+        assert_eq!((err.offset(), err.code_point()), (2, Some(0xffff)));
+    }
+
+    #[test]
+    fn test_validate_utf8() {
+        assert_eq!(UnicodeScalars::validate_utf8("a\u{10ffff}".as_bytes()), Ok(()));
+        // encoded surrogate
+        let err = UnicodeScalars::validate_utf8(b"ab\xed\xa0\x80").unwrap_err();
+        assert_eq!((err.offset(), err.code_point()), (2, None));
+        assert_eq!(err.violation(), Violation::Utf8EncodedSurrogate);
+        // a violation before the invalid sequence is reported first
+        let err = UnicodeAssignables::validate_utf8(b"a\x01b\xff").unwrap_err();
+        assert_eq!((err.offset(), err.code_point()), (1, Some(0x1)));
+        let err = XmlCharacters::validate_utf8(b"abc\xc3").unwrap_err();
+        assert_eq!((err.offset(), err.code_point()), (3, None));
+        assert_eq!(err.violation(), Violation::Utf8Truncated);
+        let err = UnicodeScalars::validate_utf8(b"\xe0\x80\x80").unwrap_err();
+        assert_eq!(err.violation(), Violation::Utf8Overlong);
+    }
+
+    #[test]
+    fn test_classify() {
+        for c in 0..=u32::MAX {
+            assert_eq!(UnicodeScalars::classify(c).is_ok(), UnicodeScalars::contains(c));
+            assert_eq!(XmlCharacters::classify(c).is_ok(), XmlCharacters::contains(c));
+            assert_eq!(UnicodeAssignables::classify(c).is_ok(), UnicodeAssignables::contains(c));
+        }
+        assert_eq!(UnicodeAssignables::classify(0x7f), Err(Violation::Delete));
+        assert_eq!(UnicodeAssignables::classify(0x1b), Err(Violation::C0Control));
+        assert_eq!(UnicodeAssignables::classify(0x9b), Err(Violation::C1Control));
+        assert_eq!(UnicodeAssignables::classify(0xdc00), Err(Violation::Surrogate));
+        assert_eq!(UnicodeAssignables::classify(0xfdd0), Err(Violation::Noncharacter));
+        assert_eq!(UnicodeAssignables::classify(0x110000), Err(Violation::OutOfRange));
+        assert_eq!(XmlCharacters::classify(0x7f), Ok(()));
+        assert_eq!(XmlCharacters::classify(0xfffe), Err(Violation::Noncharacter));
+        assert_eq!(UnicodeScalars::classify(0xd800), Err(Violation::Surrogate));
+    }
 }
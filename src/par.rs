@@ -0,0 +1,225 @@
+//! Parallel validation of large UTF-8 buffers with rayon
+
+use alloc::vec::Vec;
+
+use rayon::prelude::*;
+
+use crate::{Rfc9839Subset, ValidationError};
+
+/// Chunks are never made smaller than this, to keep the scheduling overhead
+/// low
+const MIN_CHUNK: usize = 64 * 1024;
+
+/// Decodes `bytes` as UTF-8 and checks that every character is part of the
+/// subset `S`, validating chunks of the buffer in parallel.
+///
+/// Returns the same error as [`Rfc9839Subset::validate_utf8`], the earliest
+/// violation in buffer order.
+///
+/// ```
+/// use rfc9839_rs::{par_validate_utf8, UnicodeAssignables, Violation};
+///
+/// let mut dump = "{\"name\":\"caf\u{e9}\"}\n".repeat(100_000);
+/// dump.push('\u{7f}');
+/// let err = par_validate_utf8::<UnicodeAssignables>(dump.as_bytes()).unwrap_err();
+/// assert_eq!(err.offset(), dump.len() - 1);
+/// assert_eq!(err.violation(), Violation::Delete);
+/// ```
+pub fn par_validate_utf8<S: Rfc9839Subset>(bytes: &[u8]) -> Result<(), ValidationError> {
+    validate_chunks::<S>(bytes, chunk_len(bytes))
+}
+
+/// Decodes `bytes` as UTF-8 and returns every violation of the subset `S`,
+/// in buffer order, validating chunks of the buffer in parallel.
+///
+/// After a character outside of the subset, validation resumes with the next
+/// character. After malformed UTF-8, it resumes after the maximal subpart of
+/// the sequence, the bytes [`String::from_utf8_lossy`] would replace with a
+/// single U+FFFD.
+///
+/// [`String::from_utf8_lossy`]: alloc::string::String::from_utf8_lossy
+pub fn par_validate_utf8_all<S: Rfc9839Subset>(bytes: &[u8]) -> Vec<ValidationError> {
+    all_in_chunks::<S>(bytes, chunk_len(bytes))
+}
+
+fn chunk_len(bytes: &[u8]) -> usize {
+    let chunks = rayon::current_num_threads() * 4;
+    bytes.len().div_ceil(chunks).max(MIN_CHUNK)
+}
+
+const fn is_continuation(b: u8) -> bool {
+    matches!(b, 0x80..=0xbf)
+}
+
+/// Offset right after the sequence starting at `lead`, as far as its lead byte
+/// and continuation bytes go, whether it is well formed or not
+fn sequence_end(bytes: &[u8], lead: usize) -> usize {
+    let len = match bytes[lead] {
+        0xc0..=0xdf => 2,
+        0xe0..=0xef => 3,
+        0xf0..=0xf7 => 4,
+        _ => 1,
+    };
+    (lead + 1..lead + len)
+        .find(|&i| bytes.get(i).is_none_or(|&b| !is_continuation(b)))
+        .unwrap_or(lead + len)
+        .min(bytes.len())
+}
+
+/// Splits `bytes` in ranges of about `len` bytes, such that validating them
+/// separately gives the same result as validating the whole buffer
+fn split(bytes: &[u8], len: usize) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::with_capacity(bytes.len() / len + 1);
+    let mut start = 0;
+    while start < bytes.len() {
+        let mut end = start.saturating_add(len).min(bytes.len());
+        // the sequence holding `end` starts at most 3 bytes before it, split
+        // before that sequence, or after it when the chunk would be empty.
+        // Without a lead byte, `end` is a stray continuation byte
+        if end < bytes.len() {
+            let lead = (end.saturating_sub(3)..=end)
+                .rev()
+                .find(|&i| !is_continuation(bytes[i]));
+            if let Some(lead) = lead {
+                end = if lead > start {
+                    lead
+                } else {
+                    sequence_end(bytes, lead).max(end)
+                };
+            }
+        }
+        ranges.push((start, end));
+        start = end;
+    }
+    ranges
+}
+
+fn shifted(e: ValidationError, by: usize) -> ValidationError {
+    ValidationError::new(e.offset() + by, e.code_point(), e.violation())
+}
+
+fn validate_chunks<S: Rfc9839Subset>(bytes: &[u8], len: usize) -> Result<(), ValidationError> {
+    match split(bytes, len)
+        .into_par_iter()
+        .find_map_first(|(start, end)| {
+            S::validate_utf8(&bytes[start..end])
+                .err()
+                .map(|e| shifted(e, start))
+        }) {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+fn all_in_chunks<S: Rfc9839Subset>(bytes: &[u8], len: usize) -> Vec<ValidationError> {
+    let chunks: Vec<Vec<ValidationError>> = split(bytes, len)
+        .into_par_iter()
+        .map(|(start, end)| all_in_chunk::<S>(&bytes[start..end], start))
+        .collect();
+    chunks.concat()
+}
+
+/// Every violation in `chunk`, which starts at `base` in the buffer
+fn all_in_chunk<S: Rfc9839Subset>(chunk: &[u8], base: usize) -> Vec<ValidationError> {
+    let mut violations = Vec::new();
+    let mut pos = 0;
+    while let Err(e) = S::validate_utf8(&chunk[pos..]) {
+        let at = pos + e.offset();
+        violations.push(shifted(e, base + pos));
+        pos = at
+            + match e.code_point() {
+                Some(c) => char::from_u32(c).map_or(1, char::len_utf8),
+                None => core::str::from_utf8(&chunk[at..])
+                    .err()
+                    .and_then(|e| e.error_len())
+                    .unwrap_or(chunk.len() - at),
+            };
+    }
+    violations
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{UnicodeAssignables, Violation, XmlCharacters};
+
+    const INPUTS: &[&[u8]] = &[
+        b"",
+        b"plain ascii text, long enough to be split a few times",
+        "caf\u{e9} \u{1f600}\u{10ffff} \u{4e2d}\u{6587}\u{85}\u{fdd0} end".as_bytes(),
+        b"ab\xed\xa0\x80cd\xe2\x82 \x80\x80\x80\x80\x80 \xf0\x9f\x98\x80\x80 \x7f\xf4\x90",
+        b"\xc3",
+        b"\xf0\x9f\x98\x80\x80\x80\x80\x80\xe2\x82\xac\xe2\x82",
+    ];
+
+    #[test]
+    fn test_split() {
+        for input in INPUTS {
+            for len in 1..8 {
+                let ranges = split(input, len);
+                let mut next = 0;
+                for &(start, end) in &ranges {
+                    assert!(start == next && end > start);
+                    next = end;
+                }
+                assert_eq!(next, input.len());
+            }
+        }
+    }
+
+    #[test]
+    fn test_earliest() {
+        for input in INPUTS {
+            for len in 1..8 {
+                assert_eq!(
+                    validate_chunks::<UnicodeAssignables>(input, len),
+                    UnicodeAssignables::validate_utf8(input)
+                );
+                assert_eq!(
+                    validate_chunks::<XmlCharacters>(input, len),
+                    XmlCharacters::validate_utf8(input)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_all() {
+        let found = |input| -> Vec<(usize, Violation)> {
+            let all = all_in_chunks::<UnicodeAssignables>(input, usize::MAX);
+            all.iter().map(|e| (e.offset(), e.violation())).collect()
+        };
+        assert_eq!(
+            found(INPUTS[2]),
+            [
+                (10, Violation::Noncharacter),
+                (21, Violation::C1Control),
+                (23, Violation::Noncharacter)
+            ]
+        );
+        assert_eq!(
+            found(INPUTS[3]),
+            [
+                (2, Violation::Utf8EncodedSurrogate),
+                (3, Violation::InvalidUtf8),
+                (4, Violation::InvalidUtf8),
+                (7, Violation::Utf8Truncated),
+                (10, Violation::InvalidUtf8),
+                (11, Violation::InvalidUtf8),
+                (12, Violation::InvalidUtf8),
+                (13, Violation::InvalidUtf8),
+                (14, Violation::InvalidUtf8),
+                (20, Violation::InvalidUtf8),
+                (22, Violation::Delete),
+                (23, Violation::Utf8OutOfRange),
+                (24, Violation::InvalidUtf8),
+            ]
+        );
+        for input in INPUTS {
+            let sequential = all_in_chunks::<UnicodeAssignables>(input, usize::MAX);
+            for len in 1..8 {
+                assert_eq!(all_in_chunks::<UnicodeAssignables>(input, len), sequential);
+            }
+        }
+    }
+}
@@ -0,0 +1,283 @@
+use core::fmt;
+use core::ops::RangeInclusive;
+use core::str::FromStr;
+
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+
+use crate::regex::{RegexClass, RegexDialect};
+use crate::sanitize::Replacement;
+use crate::validate::ByteOrder;
+use crate::{
+    Rfc9839Subset, UnicodeAssignables, UnicodeScalars, ValidationError, Violation, XmlCharacters,
+};
+
+/// A subset selected at runtime, for example from a configuration file.
+///
+/// Profiles are parsed from and displayed as the [`Rfc9839Subset::NAME`] of
+/// the subset they stand for.
+///
+/// ```
+/// use rfc9839_rs::Profile;
+///
+/// let profile: Profile = "assignables".parse().unwrap();
+/// assert_eq!(profile, Profile::Assignables);
+/// assert!(!profile.contains(0x7f));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Profile {
+    /// See [`UnicodeScalars`]
+    Scalars,
+    /// See [`XmlCharacters`]
+    XmlCharacters,
+    /// See [`UnicodeAssignables`]
+    Assignables,
+}
+
+impl Profile {
+    /// Every profile, from the most to the least permissive
+    pub const ALL: &'static [Profile] = &[
+        Profile::Scalars,
+        Profile::XmlCharacters,
+        Profile::Assignables,
+    ];
+
+    /// Name of the profile, as accepted by [`FromStr`]
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Profile::Scalars => UnicodeScalars::NAME,
+            Profile::XmlCharacters => XmlCharacters::NAME,
+            Profile::Assignables => UnicodeAssignables::NAME,
+        }
+    }
+
+    pub const fn contains(&self, c: u32) -> bool {
+        match self {
+            Profile::Scalars => UnicodeScalars::contains(c),
+            Profile::XmlCharacters => XmlCharacters::contains(c),
+            Profile::Assignables => UnicodeAssignables::contains(c),
+        }
+    }
+
+    /// Explains why `c` isn't part of the profile
+    pub const fn classify(&self, c: u32) -> Result<(), Violation> {
+        match self {
+            Profile::Scalars => UnicodeScalars::classify(c),
+            Profile::XmlCharacters => XmlCharacters::classify(c),
+            Profile::Assignables => UnicodeAssignables::classify(c),
+        }
+    }
+
+    /// Sorted, non overlapping inclusive ranges of the code points in the profile
+    pub fn ranges(&self) -> &'static [RangeInclusive<u32>] {
+        match self {
+            Profile::Scalars => <UnicodeScalars as Rfc9839Subset>::ranges(),
+            Profile::XmlCharacters => <XmlCharacters as Rfc9839Subset>::ranges(),
+            Profile::Assignables => <UnicodeAssignables as Rfc9839Subset>::ranges(),
+        }
+    }
+
+    /// Renders the profile as a character class of `dialect`
+    pub fn regex_class(&self, dialect: RegexDialect) -> RegexClass<'static> {
+        RegexClass::new(self.ranges(), dialect)
+    }
+
+    /// Checks that every character of `s` is part of the profile, returning
+    /// the first one that isn't
+    pub fn validate_str(&self, s: &str) -> Result<(), ValidationError> {
+        match self {
+            Profile::Scalars => UnicodeScalars::validate_str(s),
+            Profile::XmlCharacters => XmlCharacters::validate_str(s),
+            Profile::Assignables => UnicodeAssignables::validate_str(s),
+        }
+    }
+
+    /// Decodes `bytes` as UTF-8 and checks that every character is part of
+    /// the profile, returning the first one that isn't
+    pub fn validate_utf8(&self, bytes: &[u8]) -> Result<(), ValidationError> {
+        match self {
+            Profile::Scalars => UnicodeScalars::validate_utf8(bytes),
+            Profile::XmlCharacters => XmlCharacters::validate_utf8(bytes),
+            Profile::Assignables => UnicodeAssignables::validate_utf8(bytes),
+        }
+    }
+
+    /// Decodes `units` as UTF-16 and checks that every character is part of
+    /// the profile, returning the first one that isn't
+    pub fn validate_utf16(&self, units: &[u16]) -> Result<(), ValidationError> {
+        match self {
+            Profile::Scalars => UnicodeScalars::validate_utf16(units),
+            Profile::XmlCharacters => XmlCharacters::validate_utf16(units),
+            Profile::Assignables => UnicodeAssignables::validate_utf16(units),
+        }
+    }
+
+    /// Checks that every value of `units` is part of the profile, see
+    /// [`Rfc9839Subset::validate_utf32`]
+    pub fn validate_utf32(&self, units: &[u32]) -> Result<Option<ByteOrder>, ValidationError> {
+        match self {
+            Profile::Scalars => UnicodeScalars::validate_utf32(units),
+            Profile::XmlCharacters => XmlCharacters::validate_utf32(units),
+            Profile::Assignables => UnicodeAssignables::validate_utf32(units),
+        }
+    }
+
+    /// Decodes `bytes` as UTF-32 and checks that every character is part of
+    /// the profile, see [`Rfc9839Subset::validate_utf32_bytes`]
+    pub fn validate_utf32_bytes(
+        &self,
+        bytes: &[u8],
+        fallback: ByteOrder,
+    ) -> Result<Option<ByteOrder>, ValidationError> {
+        match self {
+            Profile::Scalars => UnicodeScalars::validate_utf32_bytes(bytes, fallback),
+            Profile::XmlCharacters => XmlCharacters::validate_utf32_bytes(bytes, fallback),
+            Profile::Assignables => UnicodeAssignables::validate_utf32_bytes(bytes, fallback),
+        }
+    }
+
+    /// Writes `s` to `w`, applying `replacement` to every character that isn't
+    /// part of the profile, see [`Rfc9839Subset::sanitize_to`]
+    pub fn sanitize_to<W: fmt::Write + ?Sized>(
+        &self,
+        s: &str,
+        replacement: Replacement,
+        w: &mut W,
+    ) -> fmt::Result {
+        match self {
+            Profile::Scalars => UnicodeScalars::sanitize_to(s, replacement, w),
+            Profile::XmlCharacters => XmlCharacters::sanitize_to(s, replacement, w),
+            Profile::Assignables => UnicodeAssignables::sanitize_to(s, replacement, w),
+        }
+    }
+
+    /// Applies `replacement` to every character of `s` that isn't part of the
+    /// profile, see [`Rfc9839Subset::sanitize_with`]
+    #[cfg(feature = "alloc")]
+    pub fn sanitize_with<'a>(&self, s: &'a str, replacement: Replacement) -> Cow<'a, str> {
+        match self {
+            Profile::Scalars => UnicodeScalars::sanitize_with(s, replacement),
+            Profile::XmlCharacters => XmlCharacters::sanitize_with(s, replacement),
+            Profile::Assignables => UnicodeAssignables::sanitize_with(s, replacement),
+        }
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Error returned when parsing an unknown [`Profile`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseProfileError(());
+
+impl fmt::Display for ParseProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("unknown profile, expected one of ")?;
+        for (i, p) in Profile::ALL.iter().enumerate() {
+            if i != 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "`{}`", p)?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for ParseProfileError {}
+
+impl FromStr for Profile {
+    type Err = ParseProfileError;
+
+    /// Parses the name of a profile, ignoring ASCII case.
+    ///
+    /// The longer names `unicode-scalars`, `xml-characters` and
+    /// `unicode-assignables` are accepted as well.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        const ALIASES: &[(&str, Profile)] = &[
+            ("unicode-scalars", Profile::Scalars),
+            ("xml-characters", Profile::XmlCharacters),
+            ("unicode-assignables", Profile::Assignables),
+        ];
+        Profile::ALL
+            .iter()
+            .map(|&p| (p.name(), p))
+            .chain(ALIASES.iter().copied())
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|(_, p)| p)
+            .ok_or(ParseProfileError(()))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Profile {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for Profile {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ProfileVisitor;
+
+        impl serde::de::Visitor<'_> for ProfileVisitor {
+            type Value = Profile;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("an RFC9839 profile name")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Profile, E> {
+                const NAMES: &[&str] = &[
+                    UnicodeScalars::NAME,
+                    XmlCharacters::NAME,
+                    UnicodeAssignables::NAME,
+                ];
+                v.parse().map_err(|_| E::unknown_variant(v, NAMES))
+            }
+        }
+
+        deserializer.deserialize_str(ProfileVisitor)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        for &p in Profile::ALL {
+            assert_eq!(p.to_string().parse(), Ok(p));
+        }
+        assert_eq!("XML-Characters".parse(), Ok(Profile::XmlCharacters));
+        assert_eq!("unicode-assignables".parse(), Ok(Profile::Assignables));
+        assert_eq!("ascii".parse::<Profile>(), Err(ParseProfileError(())));
+    }
+
+    #[test]
+    fn test_dispatch() {
+        for c in 0..=0x110000 {
+            assert_eq!(Profile::Scalars.classify(c), UnicodeScalars::classify(c));
+            assert_eq!(Profile::XmlCharacters.classify(c), XmlCharacters::classify(c));
+            assert_eq!(Profile::Assignables.classify(c), UnicodeAssignables::classify(c));
+        }
+        let err = Profile::Assignables.validate_str("a\u{85}").unwrap_err();
+        assert_eq!(err.violation(), Violation::C1Control);
+        assert_eq!(Profile::XmlCharacters.validate_utf8("a\u{85}".as_bytes()), Ok(()));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde() {
+        assert_eq!(serde_json::to_string(&Profile::XmlCharacters).unwrap(), "\"xml\"");
+        let p: Profile = serde_json::from_str("\"assignables\"").unwrap();
+        assert_eq!(p, Profile::Assignables);
+        let err = serde_json::from_str::<Profile>("\"ascii\"").unwrap_err();
+        assert!(err.to_string().contains("unknown variant `ascii`"));
+    }
+}
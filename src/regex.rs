@@ -0,0 +1,184 @@
+//! Rendering of code point ranges as regular expression character classes
+
+use core::fmt;
+use core::ops::RangeInclusive;
+
+/// Regular expression syntax a character class is rendered for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum RegexDialect {
+    /// The `regex` crate, with `\x{...}` escapes
+    Rust,
+    /// PCRE2 in UTF mode, with `\x{...}` escapes
+    Pcre,
+    /// ECMAScript with the `u` or `v` flag, with `\u{...}` escapes
+    JavaScript,
+    /// Go `regexp` and RE2, with `\x{...}` escapes
+    Re2,
+    /// JSON Schema `pattern`, as ECMAScript with the `u` flag that validators
+    /// such as ajv compile it with, with `\u{...}` escapes. Unlike
+    /// [`RegexDialect::JavaScript`], surrogates are never matched, so that the
+    /// class also compiles with the `regex` crate.
+    JsonSchema,
+}
+
+impl RegexDialect {
+    /// Whether surrogates can appear in a class. The other engines reject
+    /// them, or can never match them as they only work on valid UTF-8.
+    const fn allows_surrogates(&self) -> bool {
+        matches!(self, RegexDialect::JavaScript)
+    }
+
+    fn write_code_point(&self, c: u32, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegexDialect::JavaScript | RegexDialect::JsonSchema => write!(f, "\\u{{{:X}}}", c),
+            _ => write!(f, "\\x{{{:X}}}", c),
+        }
+    }
+}
+
+/// A set of code points rendered as a character class, such as
+/// `[\x{9}-\x{A}\x{D}\x{20}-\x{D7FF}]`, when displayed.
+///
+/// An empty set is rendered as the negation of every code point, as some
+/// engines reject `[]`.
+///
+/// ```
+/// use rfc9839_rs::{RegexDialect, Rfc9839Subset, UnicodeScalars};
+///
+/// let class = UnicodeScalars::regex_class(RegexDialect::JavaScript);
+/// assert_eq!(class.to_string(), r"[\u{0}-\u{D7FF}\u{E000}-\u{10FFFF}]");
+/// ```
+#[derive(Debug, Clone)]
+pub struct RegexClass<'a> {
+    ranges: &'a [RangeInclusive<u32>],
+    dialect: RegexDialect,
+}
+
+impl<'a> RegexClass<'a> {
+    pub(crate) const fn new(ranges: &'a [RangeInclusive<u32>], dialect: RegexDialect) -> Self {
+        Self { ranges, dialect }
+    }
+
+    fn write_range(&self, start: u32, end: u32, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.dialect.write_code_point(start, f)?;
+        if end != start {
+            f.write_str("-")?;
+            self.dialect.write_code_point(end, f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for RegexClass<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("[")?;
+        let mut empty = true;
+        for range in self.ranges {
+            let (start, end) = (*range.start(), *range.end());
+            if self.dialect.allows_surrogates() || end < 0xd800 || start > 0xdfff {
+                self.write_range(start, end, f)?;
+                empty = false;
+                continue;
+            }
+            if start < 0xd800 {
+                self.write_range(start, 0xd7ff, f)?;
+                empty = false;
+            }
+            if end > 0xdfff {
+                self.write_range(0xe000, end, f)?;
+                empty = false;
+            }
+        }
+        if empty {
+            f.write_str("^")?;
+            self.write_range(0, 0x10ffff, f)?;
+        }
+        f.write_str("]")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{CodePointSet, Rfc9839Subset, UnicodeAssignables, XmlCharacters};
+    use std::string::ToString;
+
+    #[test]
+    fn test_dialects() {
+        let class = XmlCharacters::regex_class(RegexDialect::Pcre).to_string();
+        assert_eq!(
+            class,
+            r"[\x{9}-\x{A}\x{D}\x{20}-\x{D7FF}\x{E000}-\x{FFFD}\x{10000}-\x{10FFFF}]"
+        );
+        let class = XmlCharacters::regex_class(RegexDialect::JavaScript).to_string();
+        assert_eq!(
+            class,
+            r"[\u{9}-\u{A}\u{D}\u{20}-\u{D7FF}\u{E000}-\u{FFFD}\u{10000}-\u{10FFFF}]"
+        );
+
+        const EXCLUDED: CodePointSet<23> = UnicodeAssignables::SET.complement();
+        let class = EXCLUDED.regex_class(RegexDialect::Re2).to_string();
+        assert!(
+            class.starts_with(r"[\x{0}-\x{8}\x{B}-\x{C}\x{E}-\x{1F}\x{7F}-\x{9F}\x{FDD0}-\x{FDEF}")
+        );
+        let class = EXCLUDED.regex_class(RegexDialect::JavaScript).to_string();
+        assert!(class.contains(r"\u{9F}\u{D800}-\u{DFFF}\u{FDD0}"));
+
+        const SURROGATES: CodePointSet<1> = CodePointSet::from_ranges(&[0xd800..=0xdfff]);
+        let class = SURROGATES.regex_class(RegexDialect::Rust).to_string();
+        assert_eq!(class, r"[^\x{0}-\x{10FFFF}]");
+        let class = SURROGATES.regex_class(RegexDialect::JavaScript).to_string();
+        assert_eq!(class, r"[\u{D800}-\u{DFFF}]");
+    }
+
+    #[test]
+    fn test_json_schema_dialect() {
+        let class = UnicodeAssignables::regex_class(RegexDialect::JsonSchema).to_string();
+        assert!(class.starts_with(r"[\u{9}-\u{A}\u{D}\u{20}-\u{7E}\u{A0}-\u{D7FF}\u{E000}"));
+        assert!(class.ends_with(r"\u{100000}-\u{10FFFD}]"));
+
+        const SETS: &[&[RangeInclusive<u32>]] = &[
+            UnicodeAssignables::RANGES,
+            XmlCharacters::RANGES,
+            &[0x41..=0x5a, 0x1f600..=0x1f64f],
+            &[0xd800..=0xdfff, 0x1d400..=0x1d7ff],
+            &[],
+        ];
+        for ranges in SETS {
+            let class = RegexClass::new(ranges, RegexDialect::JsonSchema).to_string();
+            let pattern = std::format!("^{}$", class);
+            // as ECMAScript with the `u` flag, and with the `regex` crate
+            let ecmascript = regress::Regex::with_flags(&pattern, "u").unwrap();
+            let rust = ::regex::Regex::new(&pattern).unwrap();
+            let boundaries = ranges.iter().flat_map(|r| {
+                let (start, end) = (*r.start(), *r.end());
+                [start.saturating_sub(1), start, end, end + 1]
+            });
+            let samples = [0, 0xffff, 0x10000, 0x1f600, 0x10ffff];
+            let mut buf = [0; 4];
+            for c in boundaries.chain(samples).filter_map(char::from_u32) {
+                let contained = ranges.iter().any(|r| r.contains(&(c as u32)));
+                let s = c.encode_utf8(&mut buf);
+                assert_eq!(ecmascript.find(s).is_some(), contained, "{} {:#x}", class, c as u32);
+                assert_eq!(rust.is_match(s), contained, "{} {:#x}", class, c as u32);
+            }
+        }
+    }
+
+    #[test]
+    fn test_rust_regex() {
+        let class = UnicodeAssignables::regex_class(RegexDialect::Rust).to_string();
+        let re = ::regex::Regex::new(&std::format!("^{}$", class)).unwrap();
+        let mut buf = [0; 4];
+        for c in (0..=0x10ffff).filter_map(char::from_u32) {
+            let s = c.encode_utf8(&mut buf);
+            assert_eq!(
+                re.is_match(s),
+                UnicodeAssignables::contains(c as u32),
+                "{:#x}",
+                c as u32
+            );
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Replacement or removal of the code points rejected by a subset
+
+use core::fmt;
+
+#[cfg(feature = "alloc")]
+use alloc::{borrow::Cow, string::String};
+
+use crate::Violation;
+
+/// What to do with the code points rejected by a subset when sanitizing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Replacement {
+    /// Replace them with U+FFFD REPLACEMENT CHARACTER
+    #[default]
+    ReplacementCharacter,
+    /// Replace them with the given character
+    Char(char),
+    /// Remove them
+    Remove,
+}
+
+impl Replacement {
+    /// The character rejected code points are replaced with, if any
+    pub const fn as_char(&self) -> Option<char> {
+        match self {
+            Replacement::ReplacementCharacter => Some(char::REPLACEMENT_CHARACTER),
+            Replacement::Char(c) => Some(*c),
+            Replacement::Remove => None,
+        }
+    }
+
+    /// `self`, or [`Replacement::Remove`] if `classify` rejects the
+    /// character it replaces with, so that the output stays in the subset
+    fn allowed_by(self, classify: fn(u32) -> Result<(), Violation>) -> Replacement {
+        match self.as_char() {
+            Some(c) if classify(c as u32).is_err() => Replacement::Remove,
+            _ => self,
+        }
+    }
+}
+
+/// Writes `s` to `w`, applying `replacement` to every character rejected by
+/// `classify`. Rejected characters are removed if `classify` rejects the
+/// replacement as well.
+pub(crate) fn sanitize_to<W: fmt::Write + ?Sized>(
+    s: &str,
+    classify: fn(u32) -> Result<(), Violation>,
+    replacement: Replacement,
+    w: &mut W,
+) -> fmt::Result {
+    let replacement = replacement.allowed_by(classify);
+    let mut start = 0;
+    for (offset, c) in s.char_indices() {
+        if classify(c as u32).is_err() {
+            w.write_str(&s[start..offset])?;
+            if let Some(r) = replacement.as_char() {
+                w.write_char(r)?;
+            }
+            start = offset + c.len_utf8();
+        }
+    }
+    w.write_str(&s[start..])
+}
+
+/// Applies `replacement` to every character of `s` rejected by `classify`,
+/// only allocating when `s` contains such characters. Rejected characters are
+/// removed if `classify` rejects the replacement as well.
+#[cfg(feature = "alloc")]
+pub(crate) fn sanitize(
+    s: &str,
+    classify: fn(u32) -> Result<(), Violation>,
+    replacement: Replacement,
+) -> Cow<'_, str> {
+    match crate::validate::validate_str(s, classify, false) {
+        Ok(()) => Cow::Borrowed(s),
+        Err(e) => {
+            let (clean, rest) = s.split_at(e.offset());
+            let mut out = String::with_capacity(s.len());
+            out.push_str(clean);
+            // writing to a `String` never fails
+            let _ = sanitize_to(rest, classify, replacement, &mut out);
+            Cow::Owned(out)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Rfc9839Subset, UnicodeAssignables, XmlCharacters};
+
+    #[test]
+    fn test_sanitize_to() {
+        let mut out = std::string::String::new();
+        let s = "a\u{1}b\u{85}\u{fffe}c";
+        UnicodeAssignables::sanitize_to(s, Replacement::Char('?'), &mut out).unwrap();
+        assert_eq!(out, "a?b??c");
+        out.clear();
+        UnicodeAssignables::sanitize_to(s, Replacement::Remove, &mut out).unwrap();
+        assert_eq!(out, "abc");
+        out.clear();
+        XmlCharacters::sanitize_to(s, Replacement::ReplacementCharacter, &mut out).unwrap();
+        assert_eq!(out, "a\u{fffd}b\u{85}\u{fffd}c");
+        // a rejected replacement removes the characters instead
+        out.clear();
+        UnicodeAssignables::sanitize_to(s, Replacement::Char('\u{7f}'), &mut out).unwrap();
+        assert_eq!(out, "abc");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_sanitize() {
+        assert!(matches!(UnicodeAssignables::sanitize("clean\r\n"), Cow::Borrowed("clean\r\n")));
+        let out = UnicodeAssignables::sanitize("é\u{7f}\u{7f}x");
+        assert_eq!(out, "é\u{fffd}\u{fffd}x");
+        let out = UnicodeAssignables::sanitize_with("\u{7f}é\u{7f}", Replacement::Remove);
+        assert_eq!(out, "é");
+        let out = XmlCharacters::sanitize_with("a\u{1}b", Replacement::Char('\u{ffff}'));
+        assert_eq!(out, "ab");
+    }
+
+    #[test]
+    fn test_rejected_replacement() {
+        struct Ascii;
+
+        impl Rfc9839Subset for Ascii {
+            const NAME: &'static str = "ascii";
+
+            fn ranges() -> &'static [core::ops::RangeInclusive<u32>] {
+                &[0x20..=0x7e]
+            }
+
+            fn contains(c: u32) -> bool {
+                (0x20..=0x7e).contains(&c)
+            }
+        }
+
+        let mut out = std::string::String::new();
+        Ascii::sanitize_to("caf\u{e9}!", Replacement::ReplacementCharacter, &mut out).unwrap();
+        assert_eq!(out, "caf!");
+        #[cfg(feature = "alloc")]
+        assert_eq!(Ascii::sanitize("\u{e9}t\u{e9}"), "t");
+    }
+}
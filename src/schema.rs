@@ -0,0 +1,77 @@
+//! JSON Schema of the validated strings, for `schemars`
+
+use alloc::borrow::Cow;
+use alloc::format;
+
+use schemars::{JsonSchema, Schema, SchemaGenerator, json_schema};
+
+use crate::{RegexDialect, Rfc9839Subset, ValidStr, ValidString};
+
+/// Name of the schema of strings of the subset `S`
+fn schema_name<S: Rfc9839Subset>() -> Cow<'static, str> {
+    Cow::Owned(format!("rfc9839-{}", S::NAME))
+}
+
+/// A string schema whose `pattern` only matches strings of the subset `S`,
+/// annotated with `x-rfc9839` holding the name of the subset. The pattern
+/// needs the ECMAScript `u` flag, which validators such as ajv use by default
+fn schema<S: Rfc9839Subset>() -> Schema {
+    json_schema!({
+        "type": "string",
+        "pattern": format!("^{}*$", S::regex_class(RegexDialect::JsonSchema)),
+        "x-rfc9839": S::NAME,
+    })
+}
+
+impl<S: Rfc9839Subset> JsonSchema for ValidStr<S> {
+    fn schema_name() -> Cow<'static, str> {
+        schema_name::<S>()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        schema::<S>()
+    }
+}
+
+impl<S: Rfc9839Subset> JsonSchema for ValidString<S> {
+    fn schema_name() -> Cow<'static, str> {
+        schema_name::<S>()
+    }
+
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        schema::<S>()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{AssignableString, XmlStr};
+
+    #[test]
+    fn test_schema() {
+        let schema = schemars::schema_for!(AssignableString);
+        assert_eq!(schema.get("type"), Some(&"string".into()));
+        assert_eq!(schema.get("x-rfc9839"), Some(&"assignables".into()));
+        let pattern = schema.get("pattern").and_then(|p| p.as_str()).unwrap();
+        assert!(pattern.starts_with(r"^[\u{9}-\u{A}\u{D}\u{20}-\u{7E}\u{A0}-\u{D7FF}"));
+
+        // compiled as ECMAScript with the `u` flag, and with the `regex` crate
+        let ecmascript = regress::Regex::with_flags(pattern, "u").unwrap();
+        let rust = regex::Regex::new(pattern).unwrap();
+        let is_match = |s: &str| {
+            let matched = ecmascript.find(s).is_some();
+            assert_eq!(rust.is_match(s), matched, "{:?}", s);
+            matched
+        };
+        assert!(is_match("caf\u{e9}\r\n\u{1f600}\u{10000}\u{10fffd}"));
+        assert!(is_match(""));
+        assert!(!is_match("a\u{7f}"));
+        assert!(!is_match("\u{9f}"));
+        assert!(!is_match("\u{fdd0}"));
+        assert!(!is_match("\u{1fffe}"));
+        assert!(!is_match("\u{10ffff}"));
+
+        let schema = schemars::schema_for!(&XmlStr);
+        assert_eq!(schema.get("x-rfc9839"), Some(&"xml".into()));
+    }
+}
@@ -0,0 +1,521 @@
+//! serde [`Serializer`] refusing or scrubbing problematic output strings
+
+use core::char::REPLACEMENT_CHARACTER;
+use core::fmt;
+use core::marker::PhantomData;
+
+use serde::ser::{
+    self, Serialize, SerializeMap, SerializeSeq, SerializeStruct, SerializeStructVariant,
+    SerializeTuple, SerializeTupleStruct, SerializeTupleVariant, Serializer,
+};
+
+use crate::Rfc9839Subset;
+
+/// What a [`ValidatingSerializer`] does with strings and chars outside of its
+/// subset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SerializeMode {
+    /// Fail on the first offending string
+    #[default]
+    Reject,
+    /// Replace every offending code point with U+FFFD, or remove it from
+    /// strings if the subset doesn't contain U+FFFD either. Offending chars
+    /// can't be removed and fail in that case.
+    Sanitize,
+}
+
+/// Serializer checking every string, map key and `char` given to `Ser`
+/// against the subset `S`.
+///
+/// ```
+/// use rfc9839_rs::{SerializeMode, UnicodeAssignables, ValidatingSerializer};
+///
+/// let mut out = Vec::new();
+/// let mut json = serde_json::Serializer::new(&mut out);
+/// let ser = ValidatingSerializer::<_, UnicodeAssignables>::new(&mut json, SerializeMode::Sanitize);
+/// serde::Serialize::serialize(&["ok", "bell\u{7}"], ser).unwrap();
+/// assert_eq!(out, r#"["ok","bell�"]"#.as_bytes());
+/// ```
+pub struct ValidatingSerializer<Ser, S> {
+    inner: Ser,
+    mode: SerializeMode,
+    _subset: PhantomData<fn() -> S>,
+}
+
+impl<Ser, S> ValidatingSerializer<Ser, S> {
+    pub const fn new(inner: Ser, mode: SerializeMode) -> Self {
+        Self {
+            inner,
+            mode,
+            _subset: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> Ser {
+        self.inner
+    }
+}
+
+/// Serializes `value` with `serializer`, checking every string against the
+/// subset `S`
+pub fn serialize<T, Ser, S>(
+    value: &T,
+    serializer: Ser,
+    mode: SerializeMode,
+) -> Result<Ser::Ok, Ser::Error>
+where
+    T: Serialize + ?Sized,
+    Ser: Serializer,
+    S: Rfc9839Subset,
+{
+    value.serialize(ValidatingSerializer::<Ser, S>::new(serializer, mode))
+}
+
+/// Value serialized through a [`ValidatingSerializer`]
+struct Wrap<'a, T: ?Sized, S> {
+    value: &'a T,
+    mode: SerializeMode,
+    _subset: PhantomData<fn() -> S>,
+}
+
+impl<'a, T: ?Sized, S> Wrap<'a, T, S> {
+    fn new(value: &'a T, mode: SerializeMode) -> Self {
+        Self {
+            value,
+            mode,
+            _subset: PhantomData,
+        }
+    }
+}
+
+impl<T: Serialize + ?Sized, S: Rfc9839Subset> Serialize for Wrap<'_, T, S> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        self.value
+            .serialize(ValidatingSerializer::<Ser, S>::new(serializer, self.mode))
+    }
+}
+
+/// Forwards `serialize_*` methods taking a primitive untouched
+macro_rules! forward_serialize {
+    ($($method:ident: $ty:ty),*) => {
+        $(
+            fn $method(self, v: $ty) -> Result<Self::Ok, Self::Error> {
+                self.inner.$method(v)
+            }
+        )*
+    };
+}
+
+impl<Ser: Serializer, S: Rfc9839Subset> Serializer for ValidatingSerializer<Ser, S> {
+    type Ok = Ser::Ok;
+    type Error = Ser::Error;
+    type SerializeSeq = ValidatingCompound<Ser::SerializeSeq, S>;
+    type SerializeTuple = ValidatingCompound<Ser::SerializeTuple, S>;
+    type SerializeTupleStruct = ValidatingCompound<Ser::SerializeTupleStruct, S>;
+    type SerializeTupleVariant = ValidatingCompound<Ser::SerializeTupleVariant, S>;
+    type SerializeMap = ValidatingCompound<Ser::SerializeMap, S>;
+    type SerializeStruct = ValidatingCompound<Ser::SerializeStruct, S>;
+    type SerializeStructVariant = ValidatingCompound<Ser::SerializeStructVariant, S>;
+
+    forward_serialize!(
+        serialize_bool: bool, serialize_i8: i8, serialize_i16: i16, serialize_i32: i32,
+        serialize_i64: i64, serialize_i128: i128, serialize_u8: u8, serialize_u16: u16,
+        serialize_u32: u32, serialize_u64: u64, serialize_u128: u128, serialize_f32: f32,
+        serialize_f64: f64, serialize_bytes: &[u8]
+    );
+
+    fn serialize_char(self, v: char) -> Result<Ser::Ok, Ser::Error> {
+        match (S::classify(v as u32), self.mode) {
+            (Ok(()), _) => self.inner.serialize_char(v),
+            (Err(_), SerializeMode::Sanitize) if S::contains(REPLACEMENT_CHARACTER as u32) => {
+                self.inner.serialize_char(REPLACEMENT_CHARACTER)
+            }
+            (Err(violation), _) => Err(ser::Error::custom(format_args!(
+                "invalid {} char: {} U+{:04X}",
+                S::NAME,
+                violation,
+                v as u32
+            ))),
+        }
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Ser::Ok, Ser::Error> {
+        match self.mode {
+            SerializeMode::Reject => match S::validate_str(v) {
+                Ok(()) => self.inner.serialize_str(v),
+                Err(e) => Err(ser::Error::custom(format_args!(
+                    "invalid {} string: {}",
+                    S::NAME,
+                    e
+                ))),
+            },
+            SerializeMode::Sanitize => self.inner.serialize_str(&S::sanitize(v)),
+        }
+    }
+
+    fn serialize_none(self) -> Result<Ser::Ok, Ser::Error> {
+        self.inner.serialize_none()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Ser::Ok, Ser::Error> {
+        self.inner
+            .serialize_some(&Wrap::<T, S>::new(value, self.mode))
+    }
+
+    fn serialize_unit(self) -> Result<Ser::Ok, Ser::Error> {
+        self.inner.serialize_unit()
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Ser::Ok, Ser::Error> {
+        self.inner.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Ser::Ok, Ser::Error> {
+        self.inner
+            .serialize_unit_variant(name, variant_index, variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<Ser::Ok, Ser::Error> {
+        self.inner
+            .serialize_newtype_struct(name, &Wrap::<T, S>::new(value, self.mode))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Ser::Ok, Ser::Error> {
+        self.inner.serialize_newtype_variant(
+            name,
+            variant_index,
+            variant,
+            &Wrap::<T, S>::new(value, self.mode),
+        )
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, Ser::Error> {
+        let inner = self.inner.serialize_seq(len)?;
+        Ok(ValidatingCompound::new(inner, self.mode))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Ser::Error> {
+        let inner = self.inner.serialize_tuple(len)?;
+        Ok(ValidatingCompound::new(inner, self.mode))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Ser::Error> {
+        let inner = self.inner.serialize_tuple_struct(name, len)?;
+        Ok(ValidatingCompound::new(inner, self.mode))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Ser::Error> {
+        let inner = self
+            .inner
+            .serialize_tuple_variant(name, variant_index, variant, len)?;
+        Ok(ValidatingCompound::new(inner, self.mode))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, Ser::Error> {
+        let inner = self.inner.serialize_map(len)?;
+        Ok(ValidatingCompound::new(inner, self.mode))
+    }
+
+    fn serialize_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, Ser::Error> {
+        let inner = self.inner.serialize_struct(name, len)?;
+        Ok(ValidatingCompound::new(inner, self.mode))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, Ser::Error> {
+        let inner = self
+            .inner
+            .serialize_struct_variant(name, variant_index, variant, len)?;
+        Ok(ValidatingCompound::new(inner, self.mode))
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+/// Sequence, tuple, map or struct serialized through a [`ValidatingSerializer`]
+pub struct ValidatingCompound<C, S> {
+    inner: C,
+    mode: SerializeMode,
+    _subset: PhantomData<fn() -> S>,
+}
+
+impl<C, S> ValidatingCompound<C, S> {
+    fn new(inner: C, mode: SerializeMode) -> Self {
+        Self {
+            inner,
+            mode,
+            _subset: PhantomData,
+        }
+    }
+}
+
+impl<C: SerializeSeq, S: Rfc9839Subset> SerializeSeq for ValidatingCompound<C, S> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner
+            .serialize_element(&Wrap::<T, S>::new(value, self.mode))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeTuple, S: Rfc9839Subset> SerializeTuple for ValidatingCompound<C, S> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner
+            .serialize_element(&Wrap::<T, S>::new(value, self.mode))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeTupleStruct, S: Rfc9839Subset> SerializeTupleStruct for ValidatingCompound<C, S> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner
+            .serialize_field(&Wrap::<T, S>::new(value, self.mode))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeTupleVariant, S: Rfc9839Subset> SerializeTupleVariant
+    for ValidatingCompound<C, S>
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner
+            .serialize_field(&Wrap::<T, S>::new(value, self.mode))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeMap, S: Rfc9839Subset> SerializeMap for ValidatingCompound<C, S> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), C::Error> {
+        self.inner.serialize_key(&Wrap::<T, S>::new(key, self.mode))
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner
+            .serialize_value(&Wrap::<T, S>::new(value, self.mode))
+    }
+
+    fn serialize_entry<K, V>(&mut self, key: &K, value: &V) -> Result<(), C::Error>
+    where
+        K: Serialize + ?Sized,
+        V: Serialize + ?Sized,
+    {
+        self.inner.serialize_entry(
+            &Wrap::<K, S>::new(key, self.mode),
+            &Wrap::<V, S>::new(value, self.mode),
+        )
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeStruct, S: Rfc9839Subset> SerializeStruct for ValidatingCompound<C, S> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), C::Error> {
+        self.inner
+            .serialize_field(key, &Wrap::<T, S>::new(value, self.mode))
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), C::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: SerializeStructVariant, S: Rfc9839Subset> SerializeStructVariant
+    for ValidatingCompound<C, S>
+{
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), C::Error> {
+        self.inner
+            .serialize_field(key, &Wrap::<T, S>::new(value, self.mode))
+    }
+
+    fn skip_field(&mut self, key: &'static str) -> Result<(), C::Error> {
+        self.inner.skip_field(key)
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<Ser: fmt::Debug, S> fmt::Debug for ValidatingSerializer<Ser, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValidatingSerializer")
+            .field("inner", &self.inner)
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+impl<C: fmt::Debug, S> fmt::Debug for ValidatingCompound<C, S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ValidatingCompound")
+            .field("inner", &self.inner)
+            .field("mode", &self.mode)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::string::String;
+    use std::vec::Vec;
+
+    use super::*;
+    use crate::{UnicodeAssignables, XmlCharacters};
+
+    #[derive(serde::Serialize)]
+    enum Event {
+        Message { from: String, body: Vec<String> },
+        Key(char),
+    }
+
+    fn to_json<S: Rfc9839Subset, T: Serialize>(
+        value: &T,
+        mode: SerializeMode,
+    ) -> Result<String, String> {
+        let mut out = Vec::new();
+        let mut json = serde_json::Serializer::new(&mut out);
+        serialize::<_, _, S>(value, &mut json, mode).map_err(|e| e.to_string())?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_reject() {
+        let events = [
+            Event::Message {
+                from: String::from("a\u{85}"),
+                body: vec![String::from("ok")],
+            },
+            Event::Key('x'),
+        ];
+        let json = to_json::<XmlCharacters, _>(&events, SerializeMode::Reject).unwrap();
+        assert_eq!(
+            json,
+            "[{\"Message\":{\"from\":\"a\u{85}\",\"body\":[\"ok\"]}},{\"Key\":\"x\"}]"
+        );
+        let err = to_json::<UnicodeAssignables, _>(&events, SerializeMode::Reject).unwrap_err();
+        assert_eq!(
+            err,
+            "invalid assignables string: legacy C1 control character U+0085 at offset 1"
+        );
+        let err = to_json::<UnicodeAssignables, _>(&Event::Key('\u{7f}'), SerializeMode::Reject)
+            .unwrap_err();
+        assert_eq!(
+            err,
+            "invalid assignables char: DEL control character U+007F"
+        );
+    }
+
+    #[test]
+    fn test_sanitize() {
+        let mut map = BTreeMap::new();
+        map.insert("k\u{1}", Some(Event::Key('\u{fffe}')));
+        map.insert("ok", None);
+        let json = to_json::<UnicodeAssignables, _>(&map, SerializeMode::Sanitize).unwrap();
+        assert_eq!(json, "{\"k\u{fffd}\":{\"Key\":\"\u{fffd}\"},\"ok\":null}");
+    }
+
+    #[test]
+    fn test_sanitize_without_replacement_character() {
+        struct Ascii;
+
+        impl Rfc9839Subset for Ascii {
+            const NAME: &'static str = "ascii";
+
+            fn ranges() -> &'static [core::ops::RangeInclusive<u32>] {
+                &[0x20..=0x7e]
+            }
+
+            fn contains(c: u32) -> bool {
+                (0x20..=0x7e).contains(&c)
+            }
+        }
+
+        let json = to_json::<Ascii, _>(&["caf\u{e9}", "ok"], SerializeMode::Sanitize).unwrap();
+        assert_eq!(json, "[\"caf\",\"ok\"]");
+        let err = to_json::<Ascii, _>(&Event::Key('\u{e9}'), SerializeMode::Sanitize).unwrap_err();
+        assert_eq!(err, "invalid ascii char: code point excluded by the subset U+00E9");
+    }
+}
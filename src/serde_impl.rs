@@ -0,0 +1,194 @@
+//! serde support for the validated string types
+
+use core::fmt;
+use core::marker::PhantomData;
+
+#[cfg(feature = "alloc")]
+use alloc::{borrow::ToOwned, string::String};
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+#[cfg(feature = "alloc")]
+use crate::ValidString;
+use crate::{Rfc9839Subset, ValidStr, ValidationError};
+
+fn invalid<E: de::Error, S: Rfc9839Subset>(e: ValidationError) -> E {
+    E::custom(format_args!("invalid {} string: {}", S::NAME, e))
+}
+
+impl<S> Serialize for ValidStr<S> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de: 'a, 'a, S: Rfc9839Subset> Deserialize<'de> for &'a ValidStr<S> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BorrowedVisitor<S>(PhantomData<fn() -> S>);
+
+        impl<'de, S: Rfc9839Subset> Visitor<'de> for BorrowedVisitor<S> {
+            type Value = &'de str;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a borrowed string of RFC9839 {}", S::NAME)
+            }
+
+            fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Self::Value, E> {
+                Ok(v)
+            }
+        }
+
+        let s = deserializer.deserialize_str(BorrowedVisitor::<S>(PhantomData))?;
+        ValidStr::new(s).map_err(invalid::<D::Error, S>)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> Serialize for ValidString<S> {
+    fn serialize<Ser: Serializer>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<'de, S: Rfc9839Subset> Deserialize<'de> for ValidString<S> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct OwnedVisitor<S>(PhantomData<fn() -> S>);
+
+        impl<S: Rfc9839Subset> Visitor<'_> for OwnedVisitor<S> {
+            type Value = ValidString<S>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "a string of RFC9839 {}", S::NAME)
+            }
+
+            fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                ValidStr::new(v).map(ToOwned::to_owned).map_err(invalid::<E, S>)
+            }
+
+            fn visit_string<E: de::Error>(self, v: String) -> Result<Self::Value, E> {
+                ValidString::new(v).map_err(|e| invalid::<E, S>(e.validation_error()))
+            }
+        }
+
+        deserializer.deserialize_string(OwnedVisitor(PhantomData))
+    }
+}
+
+/// Serializes `value` after checking it against the subset `S`
+#[cfg(feature = "alloc")]
+fn serialize<T, S, Ser>(value: &T, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+where
+    T: AsRef<str> + ?Sized,
+    S: Rfc9839Subset,
+    Ser: Serializer,
+{
+    match S::validate_str(value.as_ref()) {
+        Ok(()) => serializer.serialize_str(value.as_ref()),
+        Err(e) => Err(serde::ser::Error::custom(format_args!(
+            "invalid {} string: {}",
+            S::NAME,
+            e
+        ))),
+    }
+}
+
+macro_rules! with_module {
+    ($(#[$attr:meta])* $name:ident, $subset:ty) => {
+        $(#[$attr])*
+        #[cfg(feature = "alloc")]
+        pub mod $name {
+            use alloc::string::String;
+
+            use serde::{Deserialize, Deserializer, Serializer};
+
+            /// Serializes `value`, failing if it contains a character outside
+            /// of the subset
+            pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                T: AsRef<str> + ?Sized,
+                S: Serializer,
+            {
+                super::serialize::<T, $subset, S>(value, serializer)
+            }
+
+            /// Deserializes a [`String`], failing if it contains a character
+            /// outside of the subset
+            pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+                crate::ValidString::<$subset>::deserialize(deserializer).map(String::from)
+            }
+        }
+    };
+}
+
+with_module!(
+    /// Validates plain string fields against [`XmlCharacters`](crate::XmlCharacters)
+    /// with `#[serde(with = "rfc9839_rs::xml")]`
+    xml,
+    crate::XmlCharacters
+);
+
+with_module!(
+    /// Validates plain string fields against [`UnicodeAssignables`](crate::UnicodeAssignables)
+    /// with `#[serde(with = "rfc9839_rs::assignables")]`
+    ///
+    /// ```
+    /// #[derive(serde::Deserialize)]
+    /// struct User {
+    ///     #[serde(with = "rfc9839_rs::assignables")]
+    ///     name: String,
+    /// }
+    ///
+    /// let err = serde_json::from_str::<User>(r#"{"name": "\u0007"}"#).err().unwrap();
+    /// assert!(err.to_string().starts_with("invalid assignables string"));
+    /// ```
+    assignables,
+    crate::UnicodeAssignables
+);
+
+#[cfg(test)]
+mod test {
+    use crate::{AssignableStr, Violation, XmlStr};
+
+    #[test]
+    fn test_borrowed() {
+        let s: &AssignableStr = serde_json::from_str("\"borrowed\"").unwrap();
+        assert_eq!(s, "borrowed");
+        let err = serde_json::from_str::<&XmlStr>("\"\u{fffe}\"").unwrap_err();
+        assert!(err.to_string().contains(Violation::Noncharacter.description()));
+        // escapes can't be borrowed
+        assert!(serde_json::from_str::<&AssignableStr>("\"\\n\"").is_err());
+        assert_eq!(serde_json::to_string(s).unwrap(), "\"borrowed\"");
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_owned() {
+        use crate::AssignableString;
+
+        #[derive(Debug, serde::Serialize, serde::Deserialize)]
+        struct Message {
+            body: AssignableString,
+            #[serde(with = "crate::xml")]
+            title: String,
+        }
+
+        let m: Message = serde_json::from_str(r#"{"body": "hi\n", "title": "\u0085"}"#).unwrap();
+        assert_eq!(m.body, "hi\n");
+        let err = serde_json::from_str::<Message>(r#"{"body": "\u009f", "title": ""}"#).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "invalid assignables string: legacy C1 control character U+009F at offset 0 \
+             at line 1 column 17"
+        );
+        let err = serde_json::from_str::<Message>(r#"{"body": "", "title": "\uffff"}"#).unwrap_err();
+        assert!(err.to_string().starts_with("invalid xml string: noncharacter U+FFFF"));
+
+        let bad = Message {
+            body: AssignableString::default(),
+            title: String::from("\u{1}"),
+        };
+        assert!(serde_json::to_string(&bad).is_err());
+    }
+}
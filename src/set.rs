@@ -0,0 +1,502 @@
+//! Sets of code points stored as sorted ranges, usable in const contexts
+
+use core::fmt;
+use core::iter::FusedIterator;
+use core::ops::RangeInclusive;
+use core::slice;
+
+use crate::regex::{RegexClass, RegexDialect};
+
+/// Last Unicode code point
+const MAX: u32 = 0x10ffff;
+
+/// A set of Unicode code points, stored as at most `N` sorted, non overlapping
+/// and non adjacent inclusive ranges.
+///
+/// Every operation is a `const fn`, so sets can be derived from each other at
+/// compile time. The capacity of the result is picked by the caller, usually
+/// inferred from the type of the constant, and the operation panics if it is
+/// too small.
+///
+/// ```
+/// use core::ops::RangeInclusive;
+/// use rfc9839_rs::{CodePointSet, Rfc9839Subset, UnicodeAssignables};
+///
+/// const PRIVATE_USE: CodePointSet<3> =
+///     CodePointSet::from_ranges(&[0xe000..=0xf8ff, 0xf0000..=0xffffd, 0x100000..=0x10fffd]);
+/// static PUBLIC: CodePointSet<20> = UnicodeAssignables::SET.difference(&PRIVATE_USE);
+///
+/// /// Unicode assignables without the private use areas
+/// struct Public;
+///
+/// impl Rfc9839Subset for Public {
+///     const NAME: &'static str = "public";
+///
+///     fn ranges() -> &'static [RangeInclusive<u32>] {
+///         PUBLIC.as_ranges()
+///     }
+///
+///     fn contains(c: u32) -> bool {
+///         PUBLIC.contains(c)
+///     }
+/// }
+///
+/// assert!(Public::validate_str("caf\u{e9}").is_ok());
+/// assert!(Public::validate_str("\u{e000}").is_err());
+/// ```
+#[derive(Clone)]
+pub struct CodePointSet<const N: usize> {
+    ranges: [RangeInclusive<u32>; N],
+    len: usize,
+}
+
+impl<const N: usize> CodePointSet<N> {
+    /// The empty set
+    pub const EMPTY: Self = Self {
+        ranges: [const { 0..=0 }; N],
+        len: 0,
+    };
+
+    /// Builds a set from ranges sorted by their start, merging the ones that
+    /// overlap or touch.
+    ///
+    /// # Panics
+    ///
+    /// If the ranges aren't sorted, if one is empty or goes past `0x10ffff`,
+    /// or if they don't fit in `N` ranges once merged.
+    pub const fn from_ranges(ranges: &[RangeInclusive<u32>]) -> Self {
+        let mut set = Self::EMPTY;
+        let mut i = 0;
+        while i < ranges.len() {
+            let (start, end) = (*ranges[i].start(), *ranges[i].end());
+            assert!(start <= end && end <= MAX, "invalid code point range");
+            assert!(
+                i == 0 || *ranges[i - 1].start() <= start,
+                "code point ranges aren't sorted"
+            );
+            set.push(start, end);
+            i += 1;
+        }
+        set
+    }
+
+    /// Sorted ranges of the set
+    pub const fn as_ranges(&self) -> &[RangeInclusive<u32>] {
+        self.ranges.split_at(self.len).0
+    }
+
+    /// Number of ranges of the set
+    pub const fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether the set holds no code point
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Iterates over the code points of the set, in order
+    pub fn members(&self) -> Members<'_> {
+        Members::new(self.as_ranges())
+    }
+
+    /// Iterates over the code points up to `0x10ffff` that aren't in the
+    /// set, in order
+    pub fn non_members(&self) -> NonMembers<'_> {
+        NonMembers::new(self.as_ranges())
+    }
+
+    /// Renders the set as a character class of `dialect`
+    pub const fn regex_class(&self, dialect: RegexDialect) -> RegexClass<'_> {
+        RegexClass::new(self.as_ranges(), dialect)
+    }
+
+    /// Checks if `c` is part of the set
+    pub const fn contains(&self, c: u32) -> bool {
+        let (mut lo, mut hi) = (0, self.len);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            if c < self.start(mid) {
+                hi = mid;
+            } else if c > self.end(mid) {
+                lo = mid + 1;
+            } else {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Checks if every code point of `start..=end` is part of the set
+    pub(crate) const fn contains_range(&self, start: u32, end: u32) -> bool {
+        let mut i = 0;
+        while i < self.len {
+            if self.start(i) <= start && end <= self.end(i) {
+                return true;
+            }
+            i += 1;
+        }
+        false
+    }
+
+    /// Code points in either set
+    ///
+    /// # Panics
+    ///
+    /// If the result doesn't fit in `O` ranges.
+    pub const fn union<const M: usize, const O: usize>(
+        &self,
+        other: &CodePointSet<M>,
+    ) -> CodePointSet<O> {
+        let mut set = CodePointSet::EMPTY;
+        let (mut i, mut j) = (0, 0);
+        while i < self.len || j < other.len {
+            if j == other.len || (i < self.len && self.start(i) <= other.start(j)) {
+                set.push(self.start(i), self.end(i));
+                i += 1;
+            } else {
+                set.push(other.start(j), other.end(j));
+                j += 1;
+            }
+        }
+        set
+    }
+
+    /// Code points in both sets
+    ///
+    /// # Panics
+    ///
+    /// If the result doesn't fit in `O` ranges.
+    pub const fn intersection<const M: usize, const O: usize>(
+        &self,
+        other: &CodePointSet<M>,
+    ) -> CodePointSet<O> {
+        let mut set = CodePointSet::EMPTY;
+        let (mut i, mut j) = (0, 0);
+        while i < self.len && j < other.len {
+            let start = max(self.start(i), other.start(j));
+            let end = min(self.end(i), other.end(j));
+            if start <= end {
+                set.push(start, end);
+            }
+            if self.end(i) < other.end(j) {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        set
+    }
+
+    /// Code points in this set but not in `other`
+    ///
+    /// # Panics
+    ///
+    /// If the result doesn't fit in `O` ranges.
+    pub const fn difference<const M: usize, const O: usize>(
+        &self,
+        other: &CodePointSet<M>,
+    ) -> CodePointSet<O> {
+        let mut set = CodePointSet::EMPTY;
+        let mut j = 0;
+        let mut i = 0;
+        while i < self.len {
+            let (mut start, end) = (self.start(i), self.end(i));
+            while j < other.len && other.end(j) < start {
+                j += 1;
+            }
+            // ranges of `other` overlapping this one cut it in pieces
+            let mut k = j;
+            let mut rest = true;
+            while k < other.len && other.start(k) <= end {
+                if other.start(k) > start {
+                    set.push(start, other.start(k) - 1);
+                }
+                if other.end(k) >= end {
+                    rest = false;
+                    break;
+                }
+                start = other.end(k) + 1;
+                k += 1;
+            }
+            if rest {
+                set.push(start, end);
+            }
+            i += 1;
+        }
+        set
+    }
+
+    /// Code points up to `0x10ffff` that aren't in the set
+    ///
+    /// # Panics
+    ///
+    /// If the result doesn't fit in `O` ranges.
+    pub const fn complement<const O: usize>(&self) -> CodePointSet<O> {
+        let mut set = CodePointSet::EMPTY;
+        let mut next = 0;
+        let mut i = 0;
+        while i < self.len {
+            if self.start(i) > next {
+                set.push(next, self.start(i) - 1);
+            }
+            next = self.end(i) + 1;
+            i += 1;
+        }
+        if next <= MAX {
+            set.push(next, MAX);
+        }
+        set
+    }
+
+    const fn start(&self, i: usize) -> u32 {
+        *self.ranges[i].start()
+    }
+
+    const fn end(&self, i: usize) -> u32 {
+        *self.ranges[i].end()
+    }
+
+    /// Adds `start..=end`, which must not start before the last range
+    const fn push(&mut self, start: u32, end: u32) {
+        if self.len > 0 && start <= self.end(self.len - 1).saturating_add(1) {
+            let last = self.len - 1;
+            if end > self.end(last) {
+                self.ranges[last] = self.start(last)..=end;
+            }
+            return;
+        }
+        assert!(self.len < N, "too many ranges for the capacity of the set");
+        self.ranges[self.len] = start..=end;
+        self.len += 1;
+    }
+}
+
+const fn min(a: u32, b: u32) -> u32 {
+    if a < b { a } else { b }
+}
+
+const fn max(a: u32, b: u32) -> u32 {
+    if a > b { a } else { b }
+}
+
+impl<const N: usize> fmt::Debug for CodePointSet<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.as_ranges()).finish()
+    }
+}
+
+impl<const N: usize, const M: usize> PartialEq<CodePointSet<M>> for CodePointSet<N> {
+    fn eq(&self, other: &CodePointSet<M>) -> bool {
+        self.as_ranges() == other.as_ranges()
+    }
+}
+
+impl<const N: usize> Eq for CodePointSet<N> {}
+
+impl<const N: usize> Default for CodePointSet<N> {
+    fn default() -> Self {
+        Self::EMPTY
+    }
+}
+
+/// Iterator over the code points of sorted ranges, see
+/// [`CodePointSet::members`] and
+/// [`Rfc9839Subset::members`](crate::Rfc9839Subset::members)
+#[derive(Debug, Clone)]
+pub struct Members<'a> {
+    ranges: slice::Iter<'a, RangeInclusive<u32>>,
+    current: RangeInclusive<u32>,
+}
+
+impl<'a> Members<'a> {
+    pub(crate) fn new(ranges: &'a [RangeInclusive<u32>]) -> Self {
+        Self {
+            ranges: ranges.iter(),
+            current: RangeInclusive::new(1, 0),
+        }
+    }
+}
+
+impl Iterator for Members<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        loop {
+            if let Some(c) = self.current.next() {
+                return Some(c);
+            }
+            self.current = self.ranges.next()?.clone();
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = |r: &RangeInclusive<u32>| r.clone().size_hint().0;
+        let n = len(&self.current) + self.ranges.clone().map(len).sum::<usize>();
+        (n, Some(n))
+    }
+}
+
+impl ExactSizeIterator for Members<'_> {}
+
+impl FusedIterator for Members<'_> {}
+
+/// Iterator over the code points up to `0x10ffff` missing from sorted
+/// ranges, see [`CodePointSet::non_members`] and
+/// [`Rfc9839Subset::non_members`](crate::Rfc9839Subset::non_members)
+#[derive(Debug, Clone)]
+pub struct NonMembers<'a> {
+    ranges: slice::Iter<'a, RangeInclusive<u32>>,
+    current: RangeInclusive<u32>,
+    /// Start of the next gap between ranges
+    next: u32,
+}
+
+impl<'a> NonMembers<'a> {
+    pub(crate) fn new(ranges: &'a [RangeInclusive<u32>]) -> Self {
+        Self {
+            ranges: ranges.iter(),
+            current: RangeInclusive::new(1, 0),
+            next: 0,
+        }
+    }
+}
+
+impl Iterator for NonMembers<'_> {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        loop {
+            if let Some(c) = self.current.next() {
+                return Some(c);
+            }
+            if self.next > MAX {
+                return None;
+            }
+            match self.ranges.next() {
+                Some(r) if *r.start() > self.next => {
+                    self.current = self.next..=*r.start() - 1;
+                    self.next = *r.end() + 1;
+                }
+                Some(r) => self.next = *r.end() + 1,
+                None => {
+                    self.current = self.next..=MAX;
+                    self.next = MAX + 1;
+                }
+            }
+        }
+    }
+}
+
+impl FusedIterator for NonMembers<'_> {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Rfc9839Subset, UnicodeAssignables, UnicodeScalars, XmlCharacters};
+
+    const SURROGATES: CodePointSet<1> = CodePointSet::from_ranges(&[0xd800..=0xdfff]);
+    const BMP: CodePointSet<1> = CodePointSet::from_ranges(&[0x0..=0xffff]);
+
+    /// Checks `set` against `p` on a sample of code points around the range
+    /// bounds of both sets
+    #[track_caller]
+    fn assert_matches<const N: usize>(set: &CodePointSet<N>, p: impl Fn(u32) -> bool) {
+        for c in (0..=0x11_0000)
+            .step_by(0x3f1)
+            .chain(0xd7f0..=0xe010)
+            .chain(0xfdc0..=0x1_0010)
+        {
+            assert_eq!(set.contains(c), p(c), "{:#x}", c);
+        }
+        for r in set.as_ranges() {
+            for c in [
+                r.start().saturating_sub(1),
+                *r.start(),
+                *r.end(),
+                r.end() + 1,
+            ] {
+                assert_eq!(set.contains(c), p(c), "{:#x}", c);
+            }
+        }
+    }
+
+    #[test]
+    fn test_from_ranges() {
+        let set: CodePointSet<2> =
+            CodePointSet::from_ranges(&[0x0..=0x10, 0x5..=0x20, 0x21..=0x30, 0x40..=0x40]);
+        assert_eq!(set.as_ranges(), &[0x0..=0x30, 0x40..=0x40]);
+        assert!(CodePointSet::<0>::EMPTY.is_empty());
+        assert!(
+            std::panic::catch_unwind(|| CodePointSet::<1>::from_ranges(&[0x0..=0x1, 0x3..=0x4]))
+                .is_err()
+        );
+        assert!(
+            std::panic::catch_unwind(|| CodePointSet::<2>::from_ranges(&[0x3..=0x4, 0x0..=0x1]))
+                .is_err()
+        );
+        assert!(
+            std::panic::catch_unwind(|| CodePointSet::<1>::from_ranges(&[0x0..=0x110000])).is_err()
+        );
+    }
+
+    #[test]
+    fn test_members() {
+        let set: CodePointSet<3> =
+            CodePointSet::from_ranges(&[0x0..=0x2, 0x5..=0x5, 0x10fffe..=0x10ffff]);
+        let members: std::vec::Vec<u32> = set.members().collect();
+        assert_eq!(members, [0x0, 0x1, 0x2, 0x5, 0x10fffe, 0x10ffff]);
+        assert_eq!(set.members().len(), 6);
+        let mut non_members = set.non_members();
+        assert_eq!(non_members.next(), Some(0x3));
+        assert_eq!(non_members.next(), Some(0x4));
+        assert_eq!(non_members.next(), Some(0x6));
+        assert_eq!(non_members.last(), Some(0x10fffd));
+        assert_eq!(CodePointSet::<0>::EMPTY.non_members().count(), 0x110000);
+
+        assert_eq!(
+            XmlCharacters::SET.members().count(),
+            XmlCharacters::SET.members().len()
+        );
+        for set in [
+            &UnicodeScalars::SET.as_ranges(),
+            &XmlCharacters::SET.as_ranges(),
+            &UnicodeAssignables::SET.as_ranges(),
+        ] {
+            let members = Members::new(set).count();
+            assert_eq!(members + NonMembers::new(set).count(), 0x110000);
+        }
+        assert!(UnicodeAssignables::non_members().all(|c| !UnicodeAssignables::contains(c)));
+        assert!(UnicodeAssignables::members().all(UnicodeAssignables::contains));
+    }
+
+    #[test]
+    fn test_algebra() {
+        const NON_BMP: CodePointSet<1> = BMP.complement();
+        assert_eq!(NON_BMP.as_ranges(), &[0x10000..=0x10ffff]);
+        const SCALARS: CodePointSet<2> = SURROGATES.complement();
+        assert_eq!(SCALARS, UnicodeScalars::SET);
+        const ALL: CodePointSet<1> = SCALARS.union(&SURROGATES);
+        assert_eq!(ALL.complement::<0>(), CodePointSet::<0>::EMPTY);
+
+        const XML_BMP: CodePointSet<4> = XmlCharacters::SET.intersection(&BMP);
+        assert_matches(&XML_BMP, |c| XmlCharacters::contains(c) && c <= 0xffff);
+        const NOT_XML: CodePointSet<5> = XmlCharacters::SET.complement();
+        assert_matches(&NOT_XML, |c| !XmlCharacters::contains(c) && c <= 0x10ffff);
+        const EXTRA: CodePointSet<18> = XmlCharacters::SET.difference(&UnicodeAssignables::SET);
+        assert_matches(&EXTRA, |c| {
+            XmlCharacters::contains(c) && !UnicodeAssignables::contains(c)
+        });
+        const EITHER: CodePointSet<5> = UnicodeAssignables::SET.union(&XmlCharacters::SET);
+        assert_eq!(EITHER, XmlCharacters::SET);
+        assert_eq!(
+            UnicodeAssignables::SET.intersection::<5, 22>(&XmlCharacters::SET),
+            UnicodeAssignables::SET
+        );
+        assert!(
+            UnicodeAssignables::SET
+                .difference::<5, 0>(&XmlCharacters::SET)
+                .is_empty()
+        );
+    }
+}
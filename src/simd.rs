@@ -0,0 +1,417 @@
+//! Vectorized skipping of plain text.
+//!
+//! Blocks are accepted without being decoded unless they hold a suspicious
+//! byte: a C0 control other than `\t`, `\n` and `\r`, `0x7f`, a C1 control
+//! (`0xc2 0x80..=0x9f`), `0xed` which starts the surrogates, `0xef 0xb7` or
+//! `0xef 0xbf` which start the BMP noncharacters, a 4 byte sequence, or
+//! anything that isn't well formed UTF-8. What is skipped is ASCII text along
+//! with the code points of [`TEXT`], the first block holding a suspicious
+//! byte is left to the UTF-8 decoder.
+
+use crate::swar;
+
+/// Length of the widest block, the decoder handles at least that many bytes
+/// before skipping again
+pub(crate) const BLOCK: usize = 32;
+
+/// Code points above ASCII made of bytes that are never suspicious. A subset
+/// must contain them along with the ASCII text for blocks to be skipped.
+pub(crate) const TEXT: &[(u32, u32)] = &[(0xa0, 0xcfff), (0xe000, 0xfdbf), (0xfe00, 0xffbf)];
+
+/// Length of the prefix of `bytes` made of whole blocks of text, using the
+/// widest vector instructions available and SWAR for the ASCII rest. The
+/// prefix always ends on a character boundary.
+#[inline]
+pub(crate) fn text_prefix(bytes: &[u8]) -> usize {
+    let skipped = vector_prefix(bytes);
+    skipped + swar::ascii_text_prefix(&bytes[skipped..])
+}
+
+/// Moves `end`, the end of whole blocks of text, back before a sequence cut
+/// by it. Sequences are at most 3 bytes long in those blocks.
+#[cfg_attr(
+    not(any(
+        target_arch = "x86_64",
+        all(target_arch = "aarch64", target_feature = "neon")
+    )),
+    allow(dead_code)
+)]
+#[inline]
+const fn char_boundary(bytes: &[u8], end: usize) -> usize {
+    if end >= 1 && bytes[end - 1] >= 0xc0 {
+        end - 1
+    } else if end >= 2 && bytes[end - 2] >= 0xe0 {
+        end - 2
+    } else {
+        end
+    }
+}
+
+#[inline]
+fn vector_prefix(bytes: &[u8]) -> usize {
+    #[cfg(target_arch = "x86_64")]
+    {
+        let mut skipped = 0;
+        if x86::has_avx2() {
+            // SAFETY: AVX2 support was just checked
+            skipped = char_boundary(bytes, unsafe { x86::prefix_avx2(bytes) });
+        }
+        // SAFETY: SSE2 is part of the x86_64 baseline
+        let rest = &bytes[skipped..];
+        skipped + char_boundary(rest, unsafe { x86::prefix_sse2(rest) })
+    }
+    #[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+    {
+        // SAFETY: NEON is enabled for the target
+        char_boundary(bytes, unsafe { neon::prefix(bytes) })
+    }
+    #[cfg(not(any(
+        target_arch = "x86_64",
+        all(target_arch = "aarch64", target_feature = "neon")
+    )))]
+    {
+        let _ = bytes;
+        0
+    }
+}
+
+/// `k` biased so that comparing bytes as signed integers orders them as
+/// unsigned ones, for the instruction sets without unsigned comparisons
+#[cfg(target_arch = "x86_64")]
+const fn biased(k: u8) -> i8 {
+    (k ^ 0x80) as i8
+}
+
+#[cfg(target_arch = "x86_64")]
+mod x86 {
+    use core::arch::x86_64::*;
+
+    use super::biased;
+
+    /// Whether AVX2 can be used, detected at runtime with `std` and at
+    /// compile time otherwise
+    #[inline]
+    pub(super) fn has_avx2() -> bool {
+        #[cfg(any(test, feature = "std"))]
+        {
+            std::is_x86_feature_detected!("avx2")
+        }
+        #[cfg(not(any(test, feature = "std")))]
+        {
+            cfg!(target_feature = "avx2")
+        }
+    }
+
+    /// Lanes of `v` holding printable ASCII, `\t`, `\n` or `\r`
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    fn ascii_text_sse2(v: __m128i) -> __m128i {
+        let eq = |k: u8| _mm_cmpeq_epi8(v, _mm_set1_epi8(k as i8));
+        // signed comparison, bytes above 0x7f are negative
+        let printable = _mm_andnot_si128(eq(0x7f), _mm_cmpgt_epi8(v, _mm_set1_epi8(0x1f)));
+        let whitespace = _mm_or_si128(eq(b'\t'), _mm_or_si128(eq(b'\n'), eq(b'\r')));
+        _mm_or_si128(printable, whitespace)
+    }
+
+    /// Lanes of `v` that are suspicious, `prev1` and `prev2` holding the
+    /// bytes one and two positions before each lane
+    #[inline]
+    #[target_feature(enable = "sse2")]
+    fn suspicious_sse2(v: __m128i, prev1: __m128i, prev2: __m128i) -> __m128i {
+        let bias = _mm_set1_epi8(i8::MIN);
+        let (b, b1, b2) = (
+            _mm_xor_si128(v, bias),
+            _mm_xor_si128(prev1, bias),
+            _mm_xor_si128(prev2, bias),
+        );
+        let lt = |x, k| _mm_cmplt_epi8(x, _mm_set1_epi8(biased(k)));
+        let gt = |x, k| _mm_cmpgt_epi8(x, _mm_set1_epi8(biased(k)));
+        let eq = |x, k: u8| _mm_cmpeq_epi8(x, _mm_set1_epi8(k as i8));
+
+        let ascii = _mm_cmpgt_epi8(v, _mm_set1_epi8(-1));
+        let control = _mm_andnot_si128(ascii_text_sse2(v), ascii);
+        // 0xc0 and 0xc1 are overlong, 0xf0 and above start 4 byte sequences
+        let lead = _mm_or_si128(
+            _mm_or_si128(_mm_and_si128(gt(b, 0xbf), lt(b, 0xc2)), eq(v, 0xed)),
+            gt(b, 0xef),
+        );
+        let second = _mm_or_si128(
+            _mm_and_si128(_mm_or_si128(eq(prev1, 0xc2), eq(prev1, 0xe0)), lt(b, 0xa0)),
+            _mm_and_si128(eq(prev1, 0xef), _mm_or_si128(eq(v, 0xb7), eq(v, 0xbf))),
+        );
+        let continuation = _mm_and_si128(gt(b, 0x7f), lt(b, 0xc0));
+        let expected = _mm_or_si128(gt(b1, 0xbf), gt(b2, 0xdf));
+        _mm_or_si128(
+            _mm_or_si128(control, lead),
+            _mm_or_si128(second, _mm_xor_si128(continuation, expected)),
+        )
+    }
+
+    #[target_feature(enable = "sse2")]
+    pub(super) unsafe fn prefix_sse2(bytes: &[u8]) -> usize {
+        let mut prev = _mm_setzero_si128();
+        let mut ascii_prev = true;
+        let mut i = 0;
+        while i + 16 <= bytes.len() {
+            // SAFETY: the 16 bytes starting at `i` are in bounds
+            let v = unsafe { _mm_loadu_si128(bytes.as_ptr().add(i).cast()) };
+            // no sequence continues into a block following an ASCII one, and
+            // `prev` then stays an ASCII block
+            if ascii_prev && _mm_movemask_epi8(ascii_text_sse2(v)) == 0xffff {
+                i += 16;
+                continue;
+            }
+            let prev1 = _mm_or_si128(_mm_slli_si128::<1>(v), _mm_srli_si128::<15>(prev));
+            let prev2 = _mm_or_si128(_mm_slli_si128::<2>(v), _mm_srli_si128::<14>(prev));
+            if _mm_movemask_epi8(suspicious_sse2(v, prev1, prev2)) != 0 {
+                break;
+            }
+            ascii_prev = _mm_movemask_epi8(v) == 0;
+            prev = v;
+            i += 16;
+        }
+        i
+    }
+
+    /// Lanes of `v` holding ASCII text, as with [`ascii_text_sse2`]
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    fn ascii_text_avx2(v: __m256i) -> __m256i {
+        let eq = |k: u8| _mm256_cmpeq_epi8(v, _mm256_set1_epi8(k as i8));
+        let printable = _mm256_andnot_si256(eq(0x7f), _mm256_cmpgt_epi8(v, _mm256_set1_epi8(0x1f)));
+        let whitespace = _mm256_or_si256(eq(b'\t'), _mm256_or_si256(eq(b'\n'), eq(b'\r')));
+        _mm256_or_si256(printable, whitespace)
+    }
+
+    /// Lanes of `v` that are suspicious, as with [`suspicious_sse2`]
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    fn suspicious_avx2(v: __m256i, prev1: __m256i, prev2: __m256i) -> __m256i {
+        let bias = _mm256_set1_epi8(i8::MIN);
+        let (b, b1, b2) = (
+            _mm256_xor_si256(v, bias),
+            _mm256_xor_si256(prev1, bias),
+            _mm256_xor_si256(prev2, bias),
+        );
+        let lt = |x, k| _mm256_cmpgt_epi8(_mm256_set1_epi8(biased(k)), x);
+        let gt = |x, k| _mm256_cmpgt_epi8(x, _mm256_set1_epi8(biased(k)));
+        let eq = |x, k: u8| _mm256_cmpeq_epi8(x, _mm256_set1_epi8(k as i8));
+
+        let ascii = _mm256_cmpgt_epi8(v, _mm256_set1_epi8(-1));
+        let control = _mm256_andnot_si256(ascii_text_avx2(v), ascii);
+        let lead = _mm256_or_si256(
+            _mm256_or_si256(_mm256_and_si256(gt(b, 0xbf), lt(b, 0xc2)), eq(v, 0xed)),
+            gt(b, 0xef),
+        );
+        let second = _mm256_or_si256(
+            _mm256_and_si256(
+                _mm256_or_si256(eq(prev1, 0xc2), eq(prev1, 0xe0)),
+                lt(b, 0xa0),
+            ),
+            _mm256_and_si256(eq(prev1, 0xef), _mm256_or_si256(eq(v, 0xb7), eq(v, 0xbf))),
+        );
+        let continuation = _mm256_and_si256(gt(b, 0x7f), lt(b, 0xc0));
+        let expected = _mm256_or_si256(gt(b1, 0xbf), gt(b2, 0xdf));
+        _mm256_or_si256(
+            _mm256_or_si256(control, lead),
+            _mm256_or_si256(second, _mm256_xor_si256(continuation, expected)),
+        )
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub(super) unsafe fn prefix_avx2(bytes: &[u8]) -> usize {
+        let mut prev = _mm256_setzero_si256();
+        let mut ascii_prev = true;
+        let mut i = 0;
+        while i + 32 <= bytes.len() {
+            // SAFETY: the 32 bytes starting at `i` are in bounds
+            let v = unsafe { _mm256_loadu_si256(bytes.as_ptr().add(i).cast()) };
+            if ascii_prev && _mm256_movemask_epi8(ascii_text_avx2(v)) == -1 {
+                i += 32;
+                continue;
+            }
+            // the high half of `prev` followed by the low half of `v`, to
+            // shift bytes across the 128 bit lanes
+            let carried = _mm256_permute2x128_si256::<0x21>(prev, v);
+            let prev1 = _mm256_alignr_epi8::<15>(v, carried);
+            let prev2 = _mm256_alignr_epi8::<14>(v, carried);
+            if _mm256_movemask_epi8(suspicious_avx2(v, prev1, prev2)) != 0 {
+                break;
+            }
+            ascii_prev = _mm256_movemask_epi8(v) == 0;
+            prev = v;
+            i += 32;
+        }
+        i
+    }
+}
+
+#[cfg(all(target_arch = "aarch64", target_feature = "neon"))]
+mod neon {
+    use core::arch::aarch64::*;
+
+    /// Lanes of `v` holding printable ASCII, `\t`, `\n` or `\r`
+    #[inline]
+    #[target_feature(enable = "neon")]
+    fn ascii_text(v: uint8x16_t) -> uint8x16_t {
+        let eq = |k| vceqq_u8(v, vdupq_n_u8(k));
+        let printable = vandq_u8(vcgeq_u8(v, vdupq_n_u8(0x20)), vcltq_u8(v, vdupq_n_u8(0x7f)));
+        let whitespace = vorrq_u8(eq(b'\t'), vorrq_u8(eq(b'\n'), eq(b'\r')));
+        vorrq_u8(printable, whitespace)
+    }
+
+    /// Lanes of `v` that are suspicious, `prev1` and `prev2` holding the
+    /// bytes one and two positions before each lane
+    #[inline]
+    #[target_feature(enable = "neon")]
+    fn suspicious(v: uint8x16_t, prev1: uint8x16_t, prev2: uint8x16_t) -> uint8x16_t {
+        let lt = |x, k| vcltq_u8(x, vdupq_n_u8(k));
+        let gt = |x, k| vcgtq_u8(x, vdupq_n_u8(k));
+        let eq = |x, k| vceqq_u8(x, vdupq_n_u8(k));
+
+        let control = vbicq_u8(lt(v, 0x80), ascii_text(v));
+        let lead = vorrq_u8(
+            vorrq_u8(vandq_u8(gt(v, 0xbf), lt(v, 0xc2)), eq(v, 0xed)),
+            gt(v, 0xef),
+        );
+        let second = vorrq_u8(
+            vandq_u8(vorrq_u8(eq(prev1, 0xc2), eq(prev1, 0xe0)), lt(v, 0xa0)),
+            vandq_u8(eq(prev1, 0xef), vorrq_u8(eq(v, 0xb7), eq(v, 0xbf))),
+        );
+        let continuation = vandq_u8(gt(v, 0x7f), lt(v, 0xc0));
+        let expected = vorrq_u8(gt(prev1, 0xbf), gt(prev2, 0xdf));
+        vorrq_u8(
+            vorrq_u8(control, lead),
+            vorrq_u8(second, veorq_u8(continuation, expected)),
+        )
+    }
+
+    #[target_feature(enable = "neon")]
+    pub(super) unsafe fn prefix(bytes: &[u8]) -> usize {
+        let mut prev = vdupq_n_u8(0);
+        let mut ascii_prev = true;
+        let mut i = 0;
+        while i + 16 <= bytes.len() {
+            // SAFETY: the 16 bytes starting at `i` are in bounds
+            let v = unsafe { vld1q_u8(bytes.as_ptr().add(i)) };
+            // no sequence continues into a block following an ASCII one, and
+            // `prev` then stays an ASCII block
+            if ascii_prev && vminvq_u8(ascii_text(v)) == 0xff {
+                i += 16;
+                continue;
+            }
+            let prev1 = vextq_u8::<15>(prev, v);
+            let prev2 = vextq_u8::<14>(prev, v);
+            if vmaxvq_u8(suspicious(v, prev1, prev2)) != 0 {
+                break;
+            }
+            ascii_prev = vmaxvq_u8(v) < 0x80;
+            prev = v;
+            i += 16;
+        }
+        i
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Whether `c` is skipped without being classified
+    fn is_text(c: char) -> bool {
+        let c = c as u32;
+        matches!(c, 0x20..=0x7e | 0x9 | 0xa | 0xd)
+            || TEXT.iter().any(|&(s, e)| (s..=e).contains(&c))
+    }
+
+    const SUPPORTED: bool = cfg!(any(
+        target_arch = "x86_64",
+        all(target_arch = "aarch64", target_feature = "neon")
+    ));
+
+    /// Checks that the prefix of `input` is made of text, and that it reaches
+    /// the block before `clean`, the length of the longest such prefix
+    fn check(input: &[u8], clean: usize) {
+        let n = text_prefix(input);
+        let skipped = core::str::from_utf8(&input[..n]).expect("prefix isn't valid UTF-8");
+        assert!(skipped.chars().all(is_text), "{:x?}", input);
+        assert!(n <= clean, "{} {} {:x?}", n, clean, input);
+        if SUPPORTED {
+            assert!(n + 2 * 16 + 2 > clean, "{} {} {:x?}", n, clean, input);
+        }
+        // SSE2 only skips what is left by AVX2 when both are available
+        #[cfg(target_arch = "x86_64")]
+        {
+            // SAFETY: SSE2 is part of the x86_64 baseline
+            let n = char_boundary(input, unsafe { x86::prefix_sse2(input) });
+            assert!(core::str::from_utf8(&input[..n]).is_ok_and(|s| s.chars().all(is_text)));
+            assert!(
+                n <= clean && n + 16 + 2 > clean,
+                "{} {} {:x?}",
+                n,
+                clean,
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_prefix() {
+        let text = "The quick brown fox\tjumps over\r\nthe lazy dog ~{}[]";
+        let ascii = text.repeat(3);
+        assert_eq!(text_prefix(&ascii.as_bytes()[..64]), 64);
+        assert_eq!(text_prefix(&ascii.as_bytes()[..71]), 64);
+        if SUPPORTED {
+            assert_eq!(vector_prefix(&ascii.as_bytes()[..64]), 64);
+        }
+
+        let bmp = "Caf\u{e9} cr\u{e8}me, \u{4e2d}\u{6587}\u{6587}\u{5b57} \u{3b1}\u{3b2}\u{3b3} \u{fb01}\u{ff21}\u{ffbf}".repeat(4);
+        check(bmp.as_bytes(), bmp.len());
+        if SUPPORTED {
+            assert!(text_prefix(bmp.as_bytes()) + 16 > bmp.len());
+        }
+    }
+
+    #[test]
+    fn test_suspicious() {
+        let base = "\u{e9}t\u{e9} \u{4e2d}\u{6587}, na\u{ef}ve \u{fe0f}".repeat(6);
+        let base = base.as_bytes();
+        let suspicious: &[&[u8]] = &[
+            b"\x00",
+            b"\x1b",
+            b"\x7f",
+            b"\xc2\x85",
+            b"\xc2\x9f",
+            b"\xed\x9f\xbf",
+            b"\xed\xa0\x80",
+            b"\xef\xb7\x90",
+            b"\xef\xbf\xbe",
+            b"\xef\xbf\xbd",
+            b"\xf0\x9f\x98\x80",
+            b"\x80",
+            b"\xbf",
+            b"\xc0\xaf",
+            b"\xc1\xbf",
+            b"\xe0\x80\xaf",
+            b"\xe0\x9f\xbf",
+            b"\xc3",
+            b"\xe4\xb8",
+            b"\xc3\x28",
+            b"\xf8",
+            b"\xff",
+        ];
+        for bad in suspicious {
+            for at in (0..base.len()).filter(|&at| core::str::from_utf8(&base[..at]).is_ok()) {
+                let input = [&base[..at], bad, &base[at..]].concat();
+                check(&input, at);
+            }
+        }
+        // not suspicious, right before and after the suspicious ones
+        for fine in [
+            "\u{a0}", "\u{cfff}", "\u{e000}", "\u{fdbf}", "\u{fe00}", "\u{ffbf}", "\u{800}",
+        ] {
+            let input = [base, fine.as_bytes(), base].concat();
+            check(&input, input.len());
+        }
+    }
+}
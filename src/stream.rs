@@ -0,0 +1,178 @@
+//! Incremental validation of UTF-8 input received in chunks
+
+use core::marker::PhantomData;
+
+use crate::utf8::{Decoder, Step};
+use crate::{Rfc9839Subset, ValidationError};
+
+/// Validates UTF-8 input received in chunks against the subset `S`.
+///
+/// Multi-byte sequences may be split across chunks, the validator keeps track
+/// of them and reports violations with their offset from the start of the
+/// stream. Once the stream is over, [`finish`](Self::finish) checks that it
+/// didn't stop in the middle of a sequence.
+///
+/// ```
+/// use rfc9839_rs::{StreamValidator, UnicodeAssignables};
+///
+/// let mut validator = StreamValidator::<UnicodeAssignables>::new();
+/// // U+00E9 split in two chunks
+/// validator.feed(b"caf\xc3").unwrap();
+/// validator.feed(b"\xa9\x7f").unwrap_err();
+/// ```
+#[derive(Debug)]
+pub struct StreamValidator<S> {
+    /// Offset of the next byte
+    offset: usize,
+    /// Offset of the first byte of the pending sequence
+    start: usize,
+    decoder: Decoder,
+    error: Option<ValidationError>,
+    _subset: PhantomData<fn() -> S>,
+}
+
+impl<S> StreamValidator<S> {
+    pub const fn new() -> Self {
+        Self {
+            offset: 0,
+            start: 0,
+            decoder: Decoder::new(),
+            error: None,
+            _subset: PhantomData,
+        }
+    }
+
+    /// Number of bytes fed to the validator so far
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Number of bytes of the sequence the stream currently stops in the
+    /// middle of
+    #[cfg(feature = "std")]
+    pub(crate) const fn pending(&self) -> usize {
+        if self.decoder.is_idle() {
+            0
+        } else {
+            self.offset - self.start
+        }
+    }
+
+    /// Checks that the stream didn't end in the middle of a sequence
+    pub const fn finish(&self) -> Result<(), ValidationError> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        match self.decoder.finish() {
+            Ok(()) => Ok(()),
+            Err(v) => Err(ValidationError::new(self.start, None, v)),
+        }
+    }
+}
+
+impl<S: Rfc9839Subset> StreamValidator<S> {
+    /// Validates the next chunk of the stream.
+    ///
+    /// Once a violation was found, the validator keeps returning it.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<(), ValidationError> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        for &b in chunk {
+            if let Err(e) = self.step(b) {
+                self.error = Some(e);
+                return Err(e);
+            }
+            self.offset += 1;
+        }
+        Ok(())
+    }
+
+    fn step(&mut self, b: u8) -> Result<(), ValidationError> {
+        if self.decoder.is_idle() {
+            self.start = self.offset;
+        }
+        match self.decoder.step(b) {
+            Step::Pending => Ok(()),
+            Step::Char(c) => {
+                S::classify(c).map_err(|v| ValidationError::new(self.start, Some(c), v))
+            }
+            Step::Error(v) => Err(ValidationError::new(self.start, None, v)),
+        }
+    }
+}
+
+// not derived, which would require `S: Clone`
+impl<S> Clone for StreamValidator<S> {
+    fn clone(&self) -> Self {
+        Self {
+            offset: self.offset,
+            start: self.start,
+            decoder: self.decoder,
+            error: self.error,
+            _subset: PhantomData,
+        }
+    }
+}
+
+impl<S> Default for StreamValidator<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{UnicodeAssignables, UnicodeScalars};
+
+    fn feed_split<S: Rfc9839Subset>(input: &[u8], at: usize) -> Result<(), ValidationError> {
+        let mut validator = StreamValidator::<S>::new();
+        let (a, b) = input.split_at(at);
+        validator.feed(a)?;
+        validator.feed(b)?;
+        validator.finish()
+    }
+
+    #[test]
+    fn test_matches_validate_utf8() {
+        let inputs: &[&[u8]] = &[
+            "plain ascii".as_bytes(),
+            "caf\u{e9} \u{1f600} \u{10ffff}".as_bytes(),
+            "a\u{85}b".as_bytes(),
+            "\u{fdd0}".as_bytes(),
+            b"ab\xed\xa0\x80",
+            b"ab\xe0\x80\x80",
+            b"\xf4\x90\x80\x80",
+            b"\xc3",
+            b"x\xf0\x9f\x98",
+            b"\x80abc",
+        ];
+        for input in inputs {
+            for at in 0..=input.len() {
+                assert_eq!(
+                    feed_split::<UnicodeAssignables>(input, at),
+                    UnicodeAssignables::validate_utf8(input),
+                    "{:x?} split at {}",
+                    input,
+                    at
+                );
+                assert_eq!(
+                    feed_split::<UnicodeScalars>(input, at),
+                    UnicodeScalars::validate_utf8(input),
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_offsets() {
+        let mut validator = StreamValidator::<UnicodeAssignables>::new();
+        validator.feed(b"hello ").unwrap();
+        validator.feed(b"world\xc2").unwrap();
+        let err = validator.feed(b"\x85").unwrap_err();
+        assert_eq!((err.offset(), err.code_point()), (11, Some(0x85)));
+        assert_eq!(validator.feed(b"ok"), Err(err));
+        assert_eq!(validator.finish(), Err(err));
+    }
+}
@@ -0,0 +1,480 @@
+//! String types that can only hold characters of a given subset
+
+use core::borrow::Borrow;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::ops::Deref;
+
+#[cfg(feature = "alloc")]
+use alloc::{borrow::ToOwned, boxed::Box, string::String};
+
+use crate::{Rfc9839Subset, UnicodeAssignables, ValidationError, XmlCharacters};
+
+/// Borrowed string slice whose characters are all part of the subset `S`.
+///
+/// It can only be built through validation, which makes it a proof that the
+/// check already happened.
+///
+/// ```
+/// use rfc9839_rs::AssignableStr;
+///
+/// let s = AssignableStr::new("hello").unwrap();
+/// assert_eq!(s.len(), 5);
+/// assert!(AssignableStr::new("\u{7f}").is_err());
+/// ```
+#[repr(transparent)]
+pub struct ValidStr<S> {
+    _subset: PhantomData<fn() -> S>,
+    inner: str,
+}
+
+/// [`ValidStr`] of [`XmlCharacters`]
+pub type XmlStr = ValidStr<XmlCharacters>;
+
+/// [`ValidStr`] of [`UnicodeAssignables`]
+pub type AssignableStr = ValidStr<UnicodeAssignables>;
+
+impl<S: Rfc9839Subset> ValidStr<S> {
+    /// Checks that every character of `s` is part of the subset
+    pub fn new(s: &str) -> Result<&Self, ValidationError> {
+        S::validate_str(s)?;
+        // SAFETY: `s` was just validated
+        Ok(unsafe { Self::new_unchecked(s) })
+    }
+}
+
+impl<S> ValidStr<S> {
+    /// Wraps `s` without validating it.
+    ///
+    /// # Safety
+    ///
+    /// Every character of `s` must be part of the subset `S`.
+    pub const unsafe fn new_unchecked(s: &str) -> &Self {
+        // SAFETY: `ValidStr` is a transparent wrapper around `str`
+        unsafe { &*(s as *const str as *const Self) }
+    }
+
+    pub const fn as_str(&self) -> &str {
+        &self.inner
+    }
+}
+
+impl<S> Deref for ValidStr<S> {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.inner
+    }
+}
+
+impl<S> AsRef<str> for ValidStr<S> {
+    fn as_ref(&self) -> &str {
+        &self.inner
+    }
+}
+
+impl<S> AsRef<[u8]> for ValidStr<S> {
+    fn as_ref(&self) -> &[u8] {
+        self.inner.as_bytes()
+    }
+}
+
+impl<S> Borrow<str> for ValidStr<S> {
+    fn borrow(&self) -> &str {
+        &self.inner
+    }
+}
+
+impl<S> Default for &ValidStr<S> {
+    fn default() -> Self {
+        // SAFETY: the empty string doesn't hold any character
+        unsafe { ValidStr::new_unchecked("") }
+    }
+}
+
+impl<'a, S: Rfc9839Subset> TryFrom<&'a str> for &'a ValidStr<S> {
+    type Error = ValidationError;
+
+    fn try_from(s: &'a str) -> Result<Self, Self::Error> {
+        ValidStr::new(s)
+    }
+}
+
+impl<'a, S> From<&'a ValidStr<S>> for &'a str {
+    fn from(s: &'a ValidStr<S>) -> Self {
+        &s.inner
+    }
+}
+
+impl<S> fmt::Debug for ValidStr<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+impl<S> fmt::Display for ValidStr<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.inner, f)
+    }
+}
+
+impl<S> PartialEq for ValidStr<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+impl<S> Eq for ValidStr<S> {}
+
+impl<S> PartialEq<str> for ValidStr<S> {
+    fn eq(&self, other: &str) -> bool {
+        &self.inner == other
+    }
+}
+
+impl<S> PartialEq<&str> for ValidStr<S> {
+    fn eq(&self, other: &&str) -> bool {
+        &self.inner == *other
+    }
+}
+
+impl<S> PartialOrd for ValidStr<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<S> Ord for ValidStr<S> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.inner.cmp(&other.inner)
+    }
+}
+
+impl<S> Hash for ValidStr<S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> ToOwned for ValidStr<S> {
+    type Owned = ValidString<S>;
+
+    fn to_owned(&self) -> ValidString<S> {
+        ValidString {
+            _subset: PhantomData,
+            inner: String::from(&self.inner),
+        }
+    }
+}
+
+/// Owned string whose characters are all part of the subset `S`.
+///
+/// It can only be built through validation, which makes it a proof that the
+/// check already happened.
+///
+/// ```
+/// use rfc9839_rs::XmlString;
+///
+/// let s = XmlString::try_from(String::from("<a/>")).unwrap();
+/// assert_eq!(s.as_str(), "<a/>");
+/// let err = XmlString::try_from(String::from("\u{1b}")).unwrap_err();
+/// assert_eq!(err.into_string(), "\u{1b}");
+/// ```
+#[cfg(feature = "alloc")]
+pub struct ValidString<S> {
+    _subset: PhantomData<fn() -> S>,
+    inner: String,
+}
+
+/// [`ValidString`] of [`XmlCharacters`]
+#[cfg(feature = "alloc")]
+pub type XmlString = ValidString<XmlCharacters>;
+
+/// [`ValidString`] of [`UnicodeAssignables`]
+#[cfg(feature = "alloc")]
+pub type AssignableString = ValidString<UnicodeAssignables>;
+
+#[cfg(feature = "alloc")]
+impl<S: Rfc9839Subset> ValidString<S> {
+    /// Checks that every character of `s` is part of the subset, giving it
+    /// back on failure
+    pub fn new(s: String) -> Result<Self, FromStringError> {
+        match S::validate_str(&s) {
+            // SAFETY: `s` was just validated
+            Ok(()) => Ok(unsafe { Self::new_unchecked(s) }),
+            Err(error) => Err(FromStringError { string: s, error }),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> ValidString<S> {
+    /// Wraps `s` without validating it.
+    ///
+    /// # Safety
+    ///
+    /// Every character of `s` must be part of the subset `S`.
+    pub const unsafe fn new_unchecked(s: String) -> Self {
+        Self {
+            _subset: PhantomData,
+            inner: s,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.inner
+    }
+
+    pub fn as_valid_str(&self) -> &ValidStr<S> {
+        // SAFETY: the content of a `ValidString` is always valid
+        unsafe { ValidStr::new_unchecked(&self.inner) }
+    }
+
+    pub fn into_string(self) -> String {
+        self.inner
+    }
+
+    /// Appends `s`, which is already known to be valid
+    pub fn push_valid_str(&mut self, s: &ValidStr<S>) {
+        self.inner.push_str(s.as_str())
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> Deref for ValidString<S> {
+    type Target = ValidStr<S>;
+
+    fn deref(&self) -> &ValidStr<S> {
+        self.as_valid_str()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> AsRef<str> for ValidString<S> {
+    fn as_ref(&self) -> &str {
+        &self.inner
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> AsRef<[u8]> for ValidString<S> {
+    fn as_ref(&self) -> &[u8] {
+        self.inner.as_bytes()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> AsRef<ValidStr<S>> for ValidString<S> {
+    fn as_ref(&self) -> &ValidStr<S> {
+        self.as_valid_str()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> Borrow<str> for ValidString<S> {
+    fn borrow(&self) -> &str {
+        &self.inner
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> Borrow<ValidStr<S>> for ValidString<S> {
+    fn borrow(&self) -> &ValidStr<S> {
+        self.as_valid_str()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> Default for ValidString<S> {
+    fn default() -> Self {
+        // SAFETY: the empty string doesn't hold any character
+        unsafe { Self::new_unchecked(String::new()) }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> Clone for ValidString<S> {
+    fn clone(&self) -> Self {
+        // SAFETY: the content of a `ValidString` is always valid
+        unsafe { Self::new_unchecked(self.inner.clone()) }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: Rfc9839Subset> TryFrom<String> for ValidString<S> {
+    type Error = FromStringError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Self::new(s)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: Rfc9839Subset> TryFrom<&str> for ValidString<S> {
+    type Error = ValidationError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        ValidStr::new(s).map(ToOwned::to_owned)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S: Rfc9839Subset> core::str::FromStr for ValidString<S> {
+    type Err = ValidationError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::try_from(s)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> From<&ValidStr<S>> for ValidString<S> {
+    fn from(s: &ValidStr<S>) -> Self {
+        s.to_owned()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> From<ValidString<S>> for String {
+    fn from(s: ValidString<S>) -> Self {
+        s.inner
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> From<ValidString<S>> for Box<str> {
+    fn from(s: ValidString<S>) -> Self {
+        s.inner.into_boxed_str()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> fmt::Debug for ValidString<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.inner, f)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> fmt::Display for ValidString<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.inner, f)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> PartialEq for ValidString<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.inner == other.inner
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> Eq for ValidString<S> {}
+
+#[cfg(feature = "alloc")]
+impl<S> PartialEq<str> for ValidString<S> {
+    fn eq(&self, other: &str) -> bool {
+        self.inner == other
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> PartialEq<&str> for ValidString<S> {
+    fn eq(&self, other: &&str) -> bool {
+        self.inner == *other
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> PartialOrd for ValidString<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<core::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> Ord for ValidString<S> {
+    fn cmp(&self, other: &Self) -> core::cmp::Ordering {
+        self.inner.cmp(&other.inner)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<S> Hash for ValidString<S> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.inner.hash(state)
+    }
+}
+
+/// Error returned when a [`String`] can't be turned into a [`ValidString`]
+#[cfg(feature = "alloc")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FromStringError {
+    string: String,
+    error: ValidationError,
+}
+
+#[cfg(feature = "alloc")]
+impl FromStringError {
+    /// The string that failed validation
+    pub fn into_string(self) -> String {
+        self.string
+    }
+
+    pub fn validation_error(&self) -> ValidationError {
+        self.error
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl fmt::Display for FromStringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(&self.error, f)
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl core::error::Error for FromStringError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.error)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::Violation;
+
+    fn takes_assignable(s: &AssignableStr) -> usize {
+        s.chars().count()
+    }
+
+    #[test]
+    fn test_valid_str() {
+        let s: &AssignableStr = "caf\u{e9}".try_into().unwrap();
+        assert_eq!(takes_assignable(s), 4);
+        assert_eq!(s, "caf\u{e9}");
+        let err = <&XmlStr>::try_from("a\u{fffe}").unwrap_err();
+        assert_eq!(err.violation(), Violation::Noncharacter);
+        assert!(XmlStr::new("a\u{85}").is_ok());
+        assert!(AssignableStr::new("a\u{85}").is_err());
+    }
+
+    #[cfg(feature = "alloc")]
+    #[test]
+    fn test_valid_string() {
+        let s: AssignableString = "line\r\n".parse().unwrap();
+        assert_eq!(takes_assignable(&s), 6);
+        let mut owned = s.clone();
+        owned.push_valid_str(AssignableStr::new("more").unwrap());
+        assert_eq!(String::from(owned), "line\r\nmore");
+
+        let err = AssignableString::try_from(String::from("\u{9f}")).unwrap_err();
+        assert_eq!(err.validation_error().code_point(), Some(0x9f));
+        assert_eq!(err.into_string(), "\u{9f}");
+        assert_eq!(AssignableString::default().as_str(), "");
+    }
+}
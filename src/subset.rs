@@ -0,0 +1,255 @@
+use core::fmt;
+use core::ops::RangeInclusive;
+
+#[cfg(feature = "alloc")]
+use alloc::borrow::Cow;
+
+use crate::regex::{RegexClass, RegexDialect};
+use crate::sanitize::{self, Replacement};
+use crate::set::{Members, NonMembers};
+use crate::validate::{self, ByteOrder, FastPaths};
+use crate::{UnicodeAssignables, UnicodeScalars, ValidationError, Violation, XmlCharacters};
+
+/// A set of Unicode code points, such as the ones defined by RFC9839.
+///
+/// Implementing this trait on a type lets it be used anywhere the subsets of
+/// this crate are accepted, only `NAME`, `ranges` and `contains` are required.
+///
+/// ```
+/// use core::ops::RangeInclusive;
+/// use rfc9839_rs::{Rfc9839Subset, UnicodeAssignables, Violation};
+///
+/// /// Unicode assignables of the Basic Multilingual Plane, without its private
+/// /// use area
+/// struct Public;
+///
+/// impl Rfc9839Subset for Public {
+///     const NAME: &'static str = "public";
+///
+///     fn ranges() -> &'static [RangeInclusive<u32>] {
+///         &[
+///             0x9..=0xa,
+///             0xd..=0xd,
+///             0x20..=0x7e,
+///             0xa0..=0xd7ff,
+///             0xf900..=0xfdcf,
+///             0xfdf0..=0xfffd,
+///         ]
+///     }
+///
+///     fn contains(c: u32) -> bool {
+///         Self::ranges().iter().any(|r| r.contains(&c))
+///     }
+/// }
+///
+/// assert_eq!(Public::classify(0xe000), Err(Violation::Excluded));
+/// assert_eq!(Public::classify(0x1f600), Err(Violation::Excluded));
+/// assert_eq!(Public::classify(0xfffd), Ok(()));
+/// assert_eq!(Public::classify(0x7f), Err(Violation::Delete));
+/// assert!(UnicodeAssignables::contains(0xe000));
+/// ```
+pub trait Rfc9839Subset {
+    /// Short name of the subset, such as `"assignables"`
+    const NAME: &'static str;
+
+    /// Sorted, non overlapping inclusive ranges of the code points in the subset
+    fn ranges() -> &'static [RangeInclusive<u32>];
+
+    /// Checks if `c` is part of the subset
+    fn contains(c: u32) -> bool;
+
+    /// Iterates over the code points of the subset, in order
+    fn members() -> Members<'static> {
+        Members::new(Self::ranges())
+    }
+
+    /// Iterates over the code points up to `0x10ffff` that aren't part of
+    /// the subset, in order
+    fn non_members() -> NonMembers<'static> {
+        NonMembers::new(Self::ranges())
+    }
+
+    /// Code points the validators may skip, only set by the subsets of this
+    /// crate
+    #[doc(hidden)]
+    const FAST_PATHS: FastPaths = FastPaths::NONE;
+
+    /// Renders the subset as a character class of `dialect`
+    fn regex_class(dialect: RegexDialect) -> RegexClass<'static> {
+        RegexClass::new(Self::ranges(), dialect)
+    }
+
+    /// Explains why `c` isn't part of the subset
+    fn classify(c: u32) -> Result<(), Violation> {
+        if Self::contains(c) {
+            Ok(())
+        } else {
+            Err(Violation::guess(c))
+        }
+    }
+
+    /// Checks that every character of `s` is part of the subset, returning the
+    /// first one that isn't
+    fn validate_str(s: &str) -> Result<(), ValidationError> {
+        validate::validate_str(s, Self::classify, Self::FAST_PATHS.utf8_text)
+    }
+
+    /// Decodes `bytes` as UTF-8 and checks that every character is part of
+    /// the subset, returning the first one that isn't
+    fn validate_utf8(bytes: &[u8]) -> Result<(), ValidationError> {
+        validate::validate_utf8(bytes, Self::classify, Self::FAST_PATHS.utf8_text)
+    }
+
+    /// Decodes `units` as UTF-16 and checks that every character is part of
+    /// the subset, returning the first one that isn't.
+    ///
+    /// Unpaired surrogates are reported as
+    /// [`Violation::LoneHighSurrogate`] or [`Violation::LoneLowSurrogate`], and
+    /// offsets are counted in `u16` code units.
+    fn validate_utf16(units: &[u16]) -> Result<(), ValidationError> {
+        validate::validate_utf16(units, Self::classify, Self::FAST_PATHS.bmp_text)
+    }
+
+    /// Checks that every value of `units` is part of the subset, returning
+    /// the first one that isn't.
+    ///
+    /// A leading byte order mark is skipped, and if it reads as `0xfffe0000`
+    /// the following units are byte swapped before being checked. On success,
+    /// returns the byte order of the mark if there was one.
+    fn validate_utf32(units: &[u32]) -> Result<Option<ByteOrder>, ValidationError> {
+        validate::validate_utf32(units, Self::classify)
+    }
+
+    /// Decodes `bytes` as UTF-32 and checks that every character is part of
+    /// the subset, returning the first one that isn't.
+    ///
+    /// A leading byte order mark picks the byte order and is skipped,
+    /// otherwise `fallback` is used. On success, returns the byte order of
+    /// the mark if there was one.
+    fn validate_utf32_bytes(
+        bytes: &[u8],
+        fallback: ByteOrder,
+    ) -> Result<Option<ByteOrder>, ValidationError> {
+        validate::validate_utf32_bytes(bytes, fallback, Self::classify)
+    }
+
+    /// Writes `s` to `w`, applying `replacement` to every character that isn't
+    /// part of the subset. If the replacement isn't part of the subset
+    /// either, those characters are removed.
+    fn sanitize_to<W: fmt::Write + ?Sized>(
+        s: &str,
+        replacement: Replacement,
+        w: &mut W,
+    ) -> fmt::Result {
+        sanitize::sanitize_to(s, Self::classify, replacement, w)
+    }
+
+    /// Replaces every character of `s` that isn't part of the subset with
+    /// U+FFFD, or removes them if the subset doesn't contain U+FFFD, only
+    /// allocating when there is something to replace
+    #[cfg(feature = "alloc")]
+    fn sanitize(s: &str) -> Cow<'_, str> {
+        sanitize::sanitize(s, Self::classify, Replacement::ReplacementCharacter)
+    }
+
+    /// Applies `replacement` to every character of `s` that isn't part of the
+    /// subset, only allocating when there is something to replace. If the
+    /// replacement isn't part of the subset either, those characters are
+    /// removed.
+    #[cfg(feature = "alloc")]
+    fn sanitize_with(s: &str, replacement: Replacement) -> Cow<'_, str> {
+        sanitize::sanitize(s, Self::classify, replacement)
+    }
+}
+
+impl Rfc9839Subset for UnicodeScalars {
+    const NAME: &'static str = "scalars";
+    const FAST_PATHS: FastPaths = FastPaths::of(&UnicodeScalars::SET);
+
+    fn ranges() -> &'static [RangeInclusive<u32>] {
+        UnicodeScalars::RANGES
+    }
+
+    fn contains(c: u32) -> bool {
+        UnicodeScalars::contains(c)
+    }
+
+    fn classify(c: u32) -> Result<(), Violation> {
+        UnicodeScalars::classify(c)
+    }
+}
+
+impl Rfc9839Subset for XmlCharacters {
+    const NAME: &'static str = "xml";
+    const FAST_PATHS: FastPaths = FastPaths::of(&XmlCharacters::SET);
+
+    fn ranges() -> &'static [RangeInclusive<u32>] {
+        XmlCharacters::RANGES
+    }
+
+    fn contains(c: u32) -> bool {
+        XmlCharacters::contains(c)
+    }
+
+    fn classify(c: u32) -> Result<(), Violation> {
+        XmlCharacters::classify(c)
+    }
+}
+
+impl Rfc9839Subset for UnicodeAssignables {
+    const NAME: &'static str = "assignables";
+    const FAST_PATHS: FastPaths = FastPaths::of(&UnicodeAssignables::SET);
+
+    fn ranges() -> &'static [RangeInclusive<u32>] {
+        UnicodeAssignables::RANGES
+    }
+
+    fn contains(c: u32) -> bool {
+        UnicodeAssignables::contains(c)
+    }
+
+    fn classify(c: u32) -> Result<(), Violation> {
+        UnicodeAssignables::classify(c)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn first_violation<S: Rfc9839Subset>(s: &str) -> Option<(usize, Violation)> {
+        S::validate_str(s).err().map(|e| (e.offset(), e.violation()))
+    }
+
+    #[test]
+    fn test_generic() {
+        let s = "a\u{7f}\u{fffe}";
+        assert_eq!(first_violation::<UnicodeScalars>(s), None);
+        assert_eq!(first_violation::<XmlCharacters>(s), Some((2, Violation::Noncharacter)));
+        assert_eq!(first_violation::<UnicodeAssignables>(s), Some((1, Violation::Delete)));
+    }
+
+    #[test]
+    fn test_default_classify() {
+        struct Ascii;
+
+        impl Rfc9839Subset for Ascii {
+            const NAME: &'static str = "ascii";
+
+            fn ranges() -> &'static [RangeInclusive<u32>] {
+                &[0x0..=0x7f]
+            }
+
+            fn contains(c: u32) -> bool {
+                c <= 0x7f
+            }
+        }
+
+        assert_eq!(Ascii::classify(0x1), Ok(()));
+        assert_eq!(Ascii::classify(0x85), Err(Violation::C1Control));
+        assert_eq!(Ascii::classify(0xe9), Err(Violation::Excluded));
+        assert_eq!(Ascii::classify(0x110000), Err(Violation::OutOfRange));
+        let err = Ascii::validate_utf8("abé".as_bytes()).unwrap_err();
+        assert_eq!((err.offset(), err.code_point()), (2, Some(0xe9)));
+    }
+}
@@ -0,0 +1,105 @@
+//! Word at a time scanning of plain text, working on the lanes of a `u64`.
+//!
+//! Used where vector instructions aren't available, and after them for the
+//! tail of the input too short to fill a vector.
+
+const ONES8: u64 = 0x0101_0101_0101_0101;
+const HIGH8: u64 = ONES8 << 7;
+const ONES16: u64 = 0x0001_0001_0001_0001;
+const HIGH16: u64 = ONES16 << 15;
+
+/// Number of UTF-16 code units in a word
+pub(crate) const UTF16_BLOCK: usize = 4;
+
+/// High bit of every lane of `x` equal to zero
+const fn zero_lanes(x: u64, high: u64) -> u64 {
+    !(((x & !high) + !high) | x) & high
+}
+
+/// High bit of every lane of `x` below the same lane of `y`, see Hacker's
+/// Delight 2-18
+const fn less_lanes(x: u64, y: u64, high: u64) -> u64 {
+    let d = (x | high) - (y & !high);
+    ((!x & y) | (!(x ^ y) & !d)) & high
+}
+
+/// High bit of every lane of `x` in `lo..=hi`
+const fn range_lanes(x: u64, lo: u64, hi: u64, high: u64) -> u64 {
+    !(less_lanes(x, lo, high) | less_lanes(hi, x, high)) & high
+}
+
+/// High bit of every lane of `x` holding printable ASCII, a tab, a line feed
+/// or a carriage return, `ones` having the low bit of every lane set
+const fn ascii_text_lanes(x: u64, ones: u64, high: u64) -> u64 {
+    range_lanes(x, 0x20 * ones, 0x7e * ones, high)
+        | zero_lanes(x ^ (b'\t' as u64 * ones), high)
+        | zero_lanes(x ^ (b'\n' as u64 * ones), high)
+        | zero_lanes(x ^ (b'\r' as u64 * ones), high)
+}
+
+/// Length of the prefix of `bytes` made of whole words of ASCII text
+pub(crate) const fn ascii_text_prefix(bytes: &[u8]) -> usize {
+    let mut rest = bytes;
+    while let Some((word, tail)) = rest.split_first_chunk::<8>() {
+        if ascii_text_lanes(u64::from_le_bytes(*word), ONES8, HIGH8) != HIGH8 {
+            break;
+        }
+        rest = tail;
+    }
+    bytes.len() - rest.len()
+}
+
+/// Length of the prefix of `units` made of whole words of UTF-16 text in the
+/// BMP, ASCII text along with `0xa0..=0xd7ff` and `0xe000..=0xfdcf`
+pub(crate) const fn bmp_text_prefix(units: &[u16]) -> usize {
+    let mut rest = units;
+    while let Some((word, tail)) = rest.split_first_chunk::<UTF16_BLOCK>() {
+        let x = word[0] as u64
+            | (word[1] as u64) << 16
+            | (word[2] as u64) << 32
+            | (word[3] as u64) << 48;
+        let text = ascii_text_lanes(x, ONES16, HIGH16)
+            | range_lanes(x, 0xa0 * ONES16, 0xd7ff * ONES16, HIGH16)
+            | range_lanes(x, 0xe000 * ONES16, 0xfdcf * ONES16, HIGH16);
+        if text != HIGH16 {
+            break;
+        }
+        rest = tail;
+    }
+    units.len() - rest.len()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const fn is_bmp_text(u: u16) -> bool {
+        matches!(u, 0x20..=0x7e | 0x9 | 0xa | 0xd | 0xa0..=0xd7ff | 0xe000..=0xfdcf)
+    }
+
+    #[test]
+    fn test_ascii_text_prefix() {
+        const CLEAN: usize = ascii_text_prefix(b"const\tfriendly\r\n");
+        assert_eq!(CLEAN, 16);
+        for b in 0..=0xff_u8 {
+            let mut input = *b"0123456789abcdefghij";
+            input[13] = b;
+            let expected = if matches!(b, 0x20..=0x7e | b'\t' | b'\n' | b'\r') {
+                16
+            } else {
+                8
+            };
+            assert_eq!(ascii_text_prefix(&input), expected, "{:#x}", b);
+        }
+    }
+
+    #[test]
+    fn test_bmp_text_prefix() {
+        for u in 0..=0xffff_u16 {
+            let mut input = [0x61, 0xe9, 0x4e2d, 0xfdcf, 0x20, 0x20, 0x20, 0x20, 0x20];
+            input[u as usize % 4] = u;
+            let expected = if is_bmp_text(u) { 8 } else { 0 };
+            assert_eq!(bmp_text_prefix(&input), expected, "{:#x}", u);
+        }
+    }
+}
@@ -0,0 +1,279 @@
+//! Table driven UTF-8 decoder, reporting why malformed input was rejected
+
+use crate::Violation;
+
+/// Byte classes, bytes of the same class have the same transitions
+const ASCII: u8 = 0;
+/// `0x80..=0x8f`
+const CONT_LOW: u8 = 1;
+/// `0x90..=0x9f`
+const CONT_MID: u8 = 2;
+/// `0xa0..=0xbf`
+const CONT_HIGH: u8 = 3;
+/// `0xc0..=0xc1`, always overlong
+const LEAD_OVERLONG: u8 = 4;
+/// `0xc2..=0xdf`
+const LEAD2: u8 = 5;
+const LEAD_E0: u8 = 6;
+/// `0xe1..=0xec` and `0xee..=0xef`
+const LEAD3: u8 = 7;
+const LEAD_ED: u8 = 8;
+const LEAD_F0: u8 = 9;
+/// `0xf1..=0xf3`
+const LEAD4: u8 = 10;
+const LEAD_F4: u8 = 11;
+/// `0xf5..=0xf7`, would decode above `0x10ffff`
+const LEAD_RANGE: u8 = 12;
+/// `0xf8..=0xff`
+const INVALID: u8 = 13;
+const CLASSES: usize = 14;
+
+const fn class(b: u8) -> u8 {
+    match b {
+        0x00..=0x7f => ASCII,
+        0x80..=0x8f => CONT_LOW,
+        0x90..=0x9f => CONT_MID,
+        0xa0..=0xbf => CONT_HIGH,
+        0xc0..=0xc1 => LEAD_OVERLONG,
+        0xc2..=0xdf => LEAD2,
+        0xe0 => LEAD_E0,
+        0xed => LEAD_ED,
+        0xe1..=0xef => LEAD3,
+        0xf0 => LEAD_F0,
+        0xf1..=0xf3 => LEAD4,
+        0xf4 => LEAD_F4,
+        0xf5..=0xf7 => LEAD_RANGE,
+        0xf8..=0xff => INVALID,
+    }
+}
+
+const CLASS_OF: [u8; 256] = {
+    let mut table = [0; 256];
+    let mut b = 0;
+    while b < 256 {
+        table[b] = class(b as u8);
+        b += 1;
+    }
+    table
+};
+
+/// Bits of a lead byte that are part of the code point
+const LEAD_MASK: [u8; CLASSES] = [
+    0x7f, 0x3f, 0x3f, 0x3f, 0x1f, 0x1f, 0x0f, 0x0f, 0x0f, 0x07, 0x07, 0x07, 0x07, 0x07,
+];
+
+/// Between sequences
+const ACCEPT: u8 = 0;
+/// Waiting for the last continuation byte
+const NEED1: u8 = 1;
+/// Waiting for two continuation bytes
+const NEED2: u8 = 2;
+/// After `0xe0`, the next byte must be in `0xa0..=0xbf`
+const AFTER_E0: u8 = 3;
+/// After `0xed`, the next byte must be in `0x80..=0x9f`
+const AFTER_ED: u8 = 4;
+/// Waiting for three continuation bytes
+const NEED3: u8 = 5;
+/// After `0xf0`, the next byte must be in `0x90..=0xbf`
+const AFTER_F0: u8 = 6;
+/// After `0xf4`, the next byte must be in `0x80..=0x8f`
+const AFTER_F4: u8 = 7;
+const STATES: usize = 8;
+
+/// Error states, never left
+const ERR_INVALID: u8 = 8;
+const ERR_OVERLONG: u8 = 9;
+const ERR_SURROGATE: u8 = 10;
+const ERR_RANGE: u8 = 11;
+const ERR_TRUNCATED: u8 = 12;
+
+const fn transition(state: u8, class: u8) -> u8 {
+    let is_cont = matches!(class, CONT_LOW | CONT_MID | CONT_HIGH);
+    match state {
+        ACCEPT => match class {
+            ASCII => ACCEPT,
+            LEAD2 => NEED1,
+            LEAD_E0 => AFTER_E0,
+            LEAD3 => NEED2,
+            LEAD_ED => AFTER_ED,
+            LEAD_F0 => AFTER_F0,
+            LEAD4 => NEED3,
+            LEAD_F4 => AFTER_F4,
+            LEAD_OVERLONG => ERR_OVERLONG,
+            LEAD_RANGE => ERR_RANGE,
+            _ => ERR_INVALID,
+        },
+        _ if !is_cont => ERR_TRUNCATED,
+        NEED1 => ACCEPT,
+        NEED2 => NEED1,
+        NEED3 => NEED2,
+        AFTER_E0 if class == CONT_HIGH => NEED1,
+        AFTER_E0 => ERR_OVERLONG,
+        AFTER_ED if class != CONT_HIGH => NEED1,
+        AFTER_ED => ERR_SURROGATE,
+        AFTER_F0 if class != CONT_LOW => NEED2,
+        AFTER_F0 => ERR_OVERLONG,
+        AFTER_F4 if class == CONT_LOW => NEED2,
+        _ => ERR_RANGE,
+    }
+}
+
+const TRANSITIONS: [[u8; CLASSES]; STATES] = {
+    let mut table = [[0; CLASSES]; STATES];
+    let mut state = 0;
+    while state < STATES {
+        let mut class = 0;
+        while class < CLASSES {
+            table[state][class] = transition(state as u8, class as u8);
+            class += 1;
+        }
+        state += 1;
+    }
+    table
+};
+
+const fn error(state: u8) -> Violation {
+    match state {
+        ERR_OVERLONG => Violation::Utf8Overlong,
+        ERR_SURROGATE => Violation::Utf8EncodedSurrogate,
+        ERR_RANGE => Violation::Utf8OutOfRange,
+        ERR_TRUNCATED => Violation::Utf8Truncated,
+        _ => Violation::InvalidUtf8,
+    }
+}
+
+/// Outcome of feeding one byte to a [`Decoder`]
+pub(crate) enum Step {
+    /// The byte is part of an unfinished sequence
+    Pending,
+    /// The byte completed the given code point
+    Char(u32),
+    /// The sequence the byte belongs to is malformed
+    Error(Violation),
+}
+
+/// Incremental UTF-8 decoder
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Decoder {
+    state: u8,
+    code_point: u32,
+}
+
+impl Decoder {
+    pub(crate) const fn new() -> Self {
+        Self {
+            state: ACCEPT,
+            code_point: 0,
+        }
+    }
+
+    /// Whether the decoder is between two sequences
+    pub(crate) const fn is_idle(&self) -> bool {
+        self.state == ACCEPT
+    }
+
+    /// Error to report if the input stopped here
+    pub(crate) const fn finish(&self) -> Result<(), Violation> {
+        if self.state == ACCEPT {
+            Ok(())
+        } else {
+            Err(Violation::Utf8Truncated)
+        }
+    }
+
+    /// Feeds the next byte of input, an error leaves the decoder ready for a
+    /// new sequence
+    #[inline]
+    pub(crate) fn step(&mut self, b: u8) -> Step {
+        let class = CLASS_OF[b as usize];
+        self.code_point = if self.state == ACCEPT {
+            (b & LEAD_MASK[class as usize]) as u32
+        } else {
+            (self.code_point << 6) | (b & 0x3f) as u32
+        };
+        let next = TRANSITIONS[self.state as usize][class as usize];
+        if next == ACCEPT {
+            self.state = ACCEPT;
+            Step::Char(self.code_point)
+        } else if (next as usize) < STATES {
+            self.state = next;
+            Step::Pending
+        } else {
+            self.state = ACCEPT;
+            Step::Error(error(next))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Decodes `bytes`, returning the offset of the first malformed sequence
+    fn first_error(bytes: &[u8]) -> Option<(usize, Violation)> {
+        let mut decoder = Decoder::new();
+        let mut start = 0;
+        for (i, &b) in bytes.iter().enumerate() {
+            if decoder.is_idle() {
+                start = i;
+            }
+            if let Step::Error(v) = decoder.step(b) {
+                return Some((start, v));
+            }
+        }
+        decoder.finish().err().map(|v| (start, v))
+    }
+
+    #[test]
+    fn test_matches_core() {
+        let mut buf = [0; 3];
+        for i in 0..=0xff_ffff_u32 {
+            buf.copy_from_slice(&i.to_be_bytes()[1..]);
+            let expected = core::str::from_utf8(&buf).err().map(|e| e.valid_up_to());
+            assert_eq!(first_error(&buf).map(|(o, _)| o), expected, "{:x?}", buf);
+        }
+        for lead in 0xf0..=0xff {
+            for second in 0x00..=0xff {
+                let buf = [lead, second, 0x80, 0x80];
+                let expected = core::str::from_utf8(&buf).err().map(|e| e.valid_up_to());
+                assert_eq!(first_error(&buf).map(|(o, _)| o), expected, "{:x?}", buf);
+            }
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(first_error(b"\xc0\x80"), Some((0, Violation::Utf8Overlong)));
+        assert_eq!(
+            first_error(b"a\xe0\x9f\xbf"),
+            Some((1, Violation::Utf8Overlong))
+        );
+        assert_eq!(
+            first_error(b"\xf0\x8f\xbf\xbf"),
+            Some((0, Violation::Utf8Overlong))
+        );
+        assert_eq!(
+            first_error(b"\xed\xa0\x80"),
+            Some((0, Violation::Utf8EncodedSurrogate))
+        );
+        assert_eq!(
+            first_error(b"\xf4\x90\x80\x80"),
+            Some((0, Violation::Utf8OutOfRange))
+        );
+        assert_eq!(
+            first_error(b"\xf5\x80\x80\x80"),
+            Some((0, Violation::Utf8OutOfRange))
+        );
+        assert_eq!(
+            first_error(b"ab\xe2\x82"),
+            Some((2, Violation::Utf8Truncated))
+        );
+        assert_eq!(
+            first_error(b"\xe2\x82a"),
+            Some((0, Violation::Utf8Truncated))
+        );
+        assert_eq!(first_error(b"\x80"), Some((0, Violation::InvalidUtf8)));
+        assert_eq!(first_error(b"\xff"), Some((0, Violation::InvalidUtf8)));
+        assert_eq!(first_error("\u{10ffff}\u{800}".as_bytes()), None);
+    }
+}
@@ -0,0 +1,520 @@
+//! Validation of whole strings and UTF-8, UTF-16 or UTF-32 buffers against a subset
+
+use core::fmt;
+
+use crate::{simd, swar};
+use crate::{CodePointSet, Violation};
+use crate::utf8::{Decoder, Step};
+
+/// Runs of code points the validators may skip without classifying them, as
+/// they are known to be part of a subset.
+///
+/// A wrong value would make the validators accept code points the subset
+/// rejects, so it is derived from the set of the subset, and the type can't be
+/// named outside of the crate, leaving the other subsets without fast paths.
+#[doc(hidden)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FastPaths {
+    /// Printable ASCII along with `\t`, `\n` and `\r`, and the code points of
+    /// [`simd::TEXT`], skipped in UTF-8
+    pub(crate) utf8_text: bool,
+    /// The ASCII text along with `0xa0..=0xd7ff` and `0xe000..=0xfdcf`
+    pub(crate) bmp_text: bool,
+}
+
+impl FastPaths {
+    pub(crate) const NONE: FastPaths = FastPaths {
+        utf8_text: false,
+        bmp_text: false,
+    };
+
+    /// The fast paths for the code points of `set`
+    pub(crate) const fn of<const N: usize>(set: &CodePointSet<N>) -> FastPaths {
+        let ascii_text =
+            set.contains_range(0x9, 0xa) && set.contains(0xd) && set.contains_range(0x20, 0x7e);
+        let mut utf8_text = ascii_text;
+        let mut i = 0;
+        while i < simd::TEXT.len() {
+            utf8_text &= set.contains_range(simd::TEXT[i].0, simd::TEXT[i].1);
+            i += 1;
+        }
+        FastPaths {
+            utf8_text,
+            bmp_text: ascii_text
+                && set.contains_range(0xa0, 0xd7ff)
+                && set.contains_range(0xe000, 0xfdcf),
+        }
+    }
+}
+
+/// Error returned when some input contains a code point that isn't part of a
+/// subset, or when it couldn't be decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ValidationError {
+    offset: usize,
+    code_point: Option<u32>,
+    violation: Violation,
+}
+
+impl ValidationError {
+    pub(crate) const fn new(offset: usize, code_point: Option<u32>, violation: Violation) -> Self {
+        Self {
+            offset,
+            code_point,
+            violation,
+        }
+    }
+
+    /// Offset of the first offending code point in the input, counted in
+    /// code units of its encoding, bytes for UTF-8 and `u16` for UTF-16.
+    ///
+    /// UTF-32 is counted in `u32`, or in bytes when validating a byte buffer.
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// The offending code point, `None` when the input couldn't be decoded.
+    ///
+    /// Lone surrogates in UTF-16 input are reported as their code point.
+    pub const fn code_point(&self) -> Option<u32> {
+        self.code_point
+    }
+
+    /// Why the input was rejected
+    pub const fn violation(&self) -> Violation {
+        self.violation
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.code_point {
+            Some(c) => write!(f, "{} U+{:04X} at offset {}", self.violation, c, self.offset),
+            None => write!(f, "{} at offset {}", self.violation, self.offset),
+        }
+    }
+}
+
+impl core::error::Error for ValidationError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        Some(&self.violation)
+    }
+}
+
+/// Checks every character of `s` against `classify`.
+///
+/// When the subset contains the text of [`FastPaths::utf8_text`], `text`
+/// lets blocks of it be skipped without being classified.
+pub(crate) fn validate_str(
+    s: &str,
+    classify: fn(u32) -> Result<(), Violation>,
+    text: bool,
+) -> Result<(), ValidationError> {
+    let mut offset = 0;
+    let mut resume = 0;
+    while offset < s.len() {
+        if text && offset >= resume {
+            offset += simd::text_prefix(&s.as_bytes()[offset..]);
+            resume = offset + simd::BLOCK;
+            if offset == s.len() {
+                break;
+            }
+        }
+        // `offset` is always on a char boundary
+        let c = s[offset..].chars().next().unwrap_or_default();
+        if let Err(v) = classify(c as u32) {
+            return Err(ValidationError::new(offset, Some(c as u32), v));
+        }
+        offset += c.len_utf8();
+    }
+    Ok(())
+}
+
+/// Decodes `bytes` as UTF-8 and checks every character against `classify`,
+/// in a single pass.
+///
+/// When the subset contains the text of [`FastPaths::utf8_text`], `text`
+/// lets blocks of it be skipped without being decoded.
+pub(crate) fn validate_utf8(
+    bytes: &[u8],
+    classify: fn(u32) -> Result<(), Violation>,
+    text: bool,
+) -> Result<(), ValidationError> {
+    let mut decoder = Decoder::new();
+    let mut start = 0;
+    let mut resume = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        if decoder.is_idle() {
+            if text && i >= resume {
+                i += simd::text_prefix(&bytes[i..]);
+                resume = i + simd::BLOCK;
+                if i == bytes.len() {
+                    break;
+                }
+            }
+            start = i;
+        }
+        match decoder.step(bytes[i]) {
+            Step::Pending => {}
+            Step::Char(c) => {
+                if let Err(v) = classify(c) {
+                    return Err(ValidationError::new(start, Some(c), v));
+                }
+            }
+            Step::Error(v) => return Err(ValidationError::new(start, None, v)),
+        }
+        i += 1;
+    }
+    decoder
+        .finish()
+        .map_err(|v| ValidationError::new(start, None, v))
+}
+
+/// Decodes `units` as UTF-16 and checks every character against `classify`.
+///
+/// When the subset contains all of the ASCII text and the usual BMP ranges,
+/// `bmp_text` lets runs of them be skipped a word at a time.
+pub(crate) fn validate_utf16(
+    units: &[u16],
+    classify: fn(u32) -> Result<(), Violation>,
+    bmp_text: bool,
+) -> Result<(), ValidationError> {
+    let mut i = 0;
+    let mut resume = 0;
+    while i < units.len() {
+        if bmp_text && i >= resume {
+            i += swar::bmp_text_prefix(&units[i..]);
+            resume = i + swar::UTF16_BLOCK;
+            if i == units.len() {
+                break;
+            }
+        }
+        let unit = units[i] as u32;
+        let (c, len) = match unit {
+            0xd800..=0xdbff => match units.get(i + 1) {
+                Some(&low @ 0xdc00..=0xdfff) => {
+                    (0x10000 + ((unit - 0xd800) << 10) + (low as u32 - 0xdc00), 2)
+                }
+                _ => {
+                    return Err(ValidationError::new(
+                        i,
+                        Some(unit),
+                        Violation::LoneHighSurrogate,
+                    ));
+                }
+            },
+            0xdc00..=0xdfff => {
+                return Err(ValidationError::new(
+                    i,
+                    Some(unit),
+                    Violation::LoneLowSurrogate,
+                ));
+            }
+            _ => (unit, 1),
+        };
+        if let Err(v) = classify(c) {
+            return Err(ValidationError::new(i, Some(c), v));
+        }
+        i += len;
+    }
+    Ok(())
+}
+
+/// Byte order of UTF-32 input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ByteOrder {
+    LittleEndian,
+    BigEndian,
+}
+
+impl ByteOrder {
+    /// Byte order of the target
+    #[cfg(target_endian = "little")]
+    pub const NATIVE: ByteOrder = ByteOrder::LittleEndian;
+    /// Byte order of the target
+    #[cfg(target_endian = "big")]
+    pub const NATIVE: ByteOrder = ByteOrder::BigEndian;
+
+    /// The other byte order
+    const fn swapped(&self) -> ByteOrder {
+        match self {
+            ByteOrder::LittleEndian => ByteOrder::BigEndian,
+            ByteOrder::BigEndian => ByteOrder::LittleEndian,
+        }
+    }
+
+    const fn decode(&self, b: [u8; 4]) -> u32 {
+        match self {
+            ByteOrder::LittleEndian => u32::from_le_bytes(b),
+            ByteOrder::BigEndian => u32::from_be_bytes(b),
+        }
+    }
+}
+
+/// Checks every value of `units` against `classify`.
+///
+/// A leading byte order mark is skipped. Read as `0xfffe0000`, it means the
+/// units were decoded in the wrong byte order, so the following ones are
+/// swapped back before being checked. Returns the byte order the units were
+/// encoded in according to the mark, if there was one.
+pub(crate) fn validate_utf32(
+    units: &[u32],
+    classify: fn(u32) -> Result<(), Violation>,
+) -> Result<Option<ByteOrder>, ValidationError> {
+    let bom = match units {
+        [0xfeff, ..] => Some(ByteOrder::NATIVE),
+        [0xfffe0000, ..] => Some(ByteOrder::NATIVE.swapped()),
+        _ => None,
+    };
+    let swap = bom == Some(ByteOrder::NATIVE.swapped());
+    let start = if bom.is_some() { 1 } else { 0 };
+    for (i, &unit) in units.iter().enumerate().skip(start) {
+        let c = if swap { unit.swap_bytes() } else { unit };
+        if let Err(v) = classify(c) {
+            return Err(ValidationError::new(i, Some(c), v));
+        }
+    }
+    Ok(bom)
+}
+
+/// Decodes `bytes` as UTF-32 and checks every character against `classify`.
+///
+/// A leading byte order mark picks the byte order and is skipped, otherwise
+/// `fallback` is used. Returns the byte order of the mark, if there was one.
+pub(crate) fn validate_utf32_bytes(
+    bytes: &[u8],
+    fallback: ByteOrder,
+    classify: fn(u32) -> Result<(), Violation>,
+) -> Result<Option<ByteOrder>, ValidationError> {
+    let bom = match bytes {
+        [0xff, 0xfe, 0x00, 0x00, ..] => Some(ByteOrder::LittleEndian),
+        [0x00, 0x00, 0xfe, 0xff, ..] => Some(ByteOrder::BigEndian),
+        _ => None,
+    };
+    let start = if bom.is_some() { 4 } else { 0 };
+    validate_utf32_units(&bytes[start..], start, bom.unwrap_or(fallback), classify)?;
+    match (bytes.len() - start) % 4 {
+        0 => Ok(bom),
+        rest => Err(ValidationError::new(
+            bytes.len() - rest,
+            None,
+            Violation::TruncatedUtf32,
+        )),
+    }
+}
+
+/// Checks every complete unit of `bytes`, `start` being their offset in the
+/// input
+fn validate_utf32_units(
+    bytes: &[u8],
+    start: usize,
+    order: ByteOrder,
+    classify: fn(u32) -> Result<(), Violation>,
+) -> Result<(), ValidationError> {
+    for (i, unit) in bytes.chunks_exact(4).enumerate() {
+        let c = order.decode([unit[0], unit[1], unit[2], unit[3]]);
+        if let Err(v) = classify(c) {
+            return Err(ValidationError::new(start + i * 4, Some(c), v));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Rfc9839Subset, UnicodeAssignables, UnicodeScalars, XmlCharacters};
+
+    fn utf16(s: &str) -> std::vec::Vec<u16> {
+        s.encode_utf16().collect()
+    }
+
+    #[test]
+    fn test_skip_ascii_text() {
+        let mut input = std::vec![b'a'; 200];
+        input[150] = b'\n';
+        assert_eq!(UnicodeAssignables::validate_utf8(&input), Ok(()));
+        for (at, bad, violation) in [
+            (0, "\u{1}", Violation::C0Control),
+            (47, "\u{7f}", Violation::Delete),
+            (75, "\u{85}", Violation::C1Control),
+            (100, "\u{fdd0}", Violation::Noncharacter),
+            (131, "\u{ffff}", Violation::Noncharacter),
+        ] {
+            let mut input = std::string::String::from_utf8(input.clone()).unwrap();
+            input.insert_str(60, "caf\u{e9} \u{1f600}");
+            input.insert_str(at, bad);
+            let err = UnicodeAssignables::validate_utf8(input.as_bytes()).unwrap_err();
+            assert_eq!((err.offset(), err.violation()), (at, violation));
+            assert_eq!(UnicodeAssignables::validate_str(&input), Err(err));
+        }
+        input[190] = 0xe2;
+        let err = XmlCharacters::validate_utf8(&input).unwrap_err();
+        assert_eq!((err.offset(), err.violation()), (190, Violation::Utf8Truncated));
+    }
+
+    #[test]
+    fn test_skip_bmp_text() {
+        fn first_error<S: Rfc9839Subset>(s: &str) -> Option<usize> {
+            s.char_indices()
+                .find(|&(_, c)| !S::contains(c as u32))
+                .map(|(i, _)| i)
+        }
+        fn check<S: Rfc9839Subset>(s: &str) {
+            let offset = S::validate_utf8(s.as_bytes()).err().map(|e| e.offset());
+            assert_eq!(offset, first_error::<S>(s), "{:?}", s);
+            let offset = S::validate_str(s).err().map(|e| e.offset());
+            assert_eq!(offset, first_error::<S>(s), "{:?}", s);
+        }
+        let text = "Fran\u{e7}ais \u{e0} l'\u{e9}cole, \u{4e2d}\u{6587}\u{5b57}\u{7b26}, \u{3b1}\u{3b2}\u{3b3}\n".repeat(4);
+        let boundaries = (0..=text.len()).filter(|&i| text.is_char_boundary(i));
+        for at in boundaries {
+            for bad in ["\u{85}", "\u{7f}", "\u{d7ff}", "\u{fdd0}", "\u{fffe}", "\u{1fffe}", "\u{1f600}"] {
+                let mut input = text.clone();
+                input.insert_str(at, bad);
+                check::<UnicodeAssignables>(&input);
+                check::<XmlCharacters>(&input);
+                check::<UnicodeScalars>(&input);
+            }
+        }
+        let mut input = text.clone().into_bytes();
+        input.insert(100, 0xa9);
+        let err = UnicodeAssignables::validate_utf8(&input).unwrap_err();
+        assert_eq!((err.offset(), err.violation()), (100, Violation::InvalidUtf8));
+    }
+
+    /// Only ASCII digits, rejecting the rest of the ASCII text
+    struct Digits;
+
+    impl Rfc9839Subset for Digits {
+        const NAME: &'static str = "digits";
+
+        fn ranges() -> &'static [core::ops::RangeInclusive<u32>] {
+            &[0x30..=0x39]
+        }
+
+        fn contains(c: u32) -> bool {
+            (0x30..=0x39).contains(&c)
+        }
+    }
+
+    #[test]
+    fn test_fast_paths() {
+        let text = FastPaths {
+            utf8_text: true,
+            bmp_text: true,
+        };
+        assert_eq!(UnicodeAssignables::FAST_PATHS, text);
+        assert_eq!(XmlCharacters::FAST_PATHS, text);
+        assert_eq!(Digits::FAST_PATHS, FastPaths::NONE);
+        const DIGITS: CodePointSet<1> = CodePointSet::from_ranges(&[0x30..=0x39]);
+        assert_eq!(FastPaths::of(&DIGITS), FastPaths::NONE);
+        const NO_CR: CodePointSet<2> = CodePointSet::from_ranges(&[0x9..=0xa, 0x20..=0x7e]);
+        assert_eq!(FastPaths::of(&NO_CR), FastPaths::NONE);
+        const ASCII: CodePointSet<3> =
+            CodePointSet::from_ranges(&[0x9..=0xa, 0xd..=0xd, 0x20..=0x7e]);
+        assert_eq!(FastPaths::of(&ASCII), FastPaths::NONE);
+        const UTF8_TEXT: CodePointSet<6> = CodePointSet::from_ranges(&[
+            0x9..=0xa,
+            0xd..=0xd,
+            0x20..=0x7e,
+            0xa0..=0xcfff,
+            0xe000..=0xfdbf,
+            0xfe00..=0xffbf,
+        ]);
+        assert_eq!(
+            FastPaths::of(&UTF8_TEXT),
+            FastPaths {
+                utf8_text: true,
+                bmp_text: false,
+            }
+        );
+
+        // a subset of this crate can't skip what another subset rejects
+        let input = [b"0123".as_slice(), &[b'a'; 96]].concat();
+        let err = Digits::validate_utf8(&input).unwrap_err();
+        assert_eq!((err.offset(), err.violation()), (4, Violation::Excluded));
+        let s = core::str::from_utf8(&input).unwrap();
+        assert_eq!(Digits::validate_str(s), Err(err));
+        let err = Digits::validate_utf16(&utf16(s)).unwrap_err();
+        assert_eq!(err.offset(), 4);
+        let err = Digits::validate_utf16(&utf16("0\u{e9}\u{e9}\u{e9}\u{e9}")).unwrap_err();
+        assert_eq!(err.offset(), 1);
+    }
+
+    #[test]
+    fn test_utf16() {
+        assert_eq!(UnicodeScalars::validate_utf16(&utf16("a\u{1f600}\u{10ffff}")), Ok(()));
+        let err = UnicodeAssignables::validate_utf16(&utf16("\u{1f600}\u{10ffff}")).unwrap_err();
+        assert_eq!((err.offset(), err.code_point()), (2, Some(0x10ffff)));
+        assert_eq!(err.violation(), Violation::Noncharacter);
+
+        let err = XmlCharacters::validate_utf16(&[0x61, 0xd83d, 0x61]).unwrap_err();
+        assert_eq!((err.offset(), err.code_point()), (1, Some(0xd83d)));
+        assert_eq!(err.violation(), Violation::LoneHighSurrogate);
+        let err = XmlCharacters::validate_utf16(&[0x61, 0xd83d]).unwrap_err();
+        assert_eq!(err.violation(), Violation::LoneHighSurrogate);
+        let err = UnicodeScalars::validate_utf16(&[0xde00, 0xd83d]).unwrap_err();
+        assert_eq!((err.offset(), err.violation()), (0, Violation::LoneLowSurrogate));
+
+        let text = utf16("plain text, caf\u{e9} \u{4e2d}\u{6587}\r\n and more of it");
+        for (at, bad, violation) in [
+            (0, 0x1b, Violation::C0Control),
+            (9, 0x85, Violation::C1Control),
+            (13, 0xfdd0, Violation::Noncharacter),
+            (22, 0xdc00, Violation::LoneLowSurrogate),
+            (30, 0xffff, Violation::Noncharacter),
+        ] {
+            let mut input = text.clone();
+            input.insert(at, bad);
+            let err = UnicodeAssignables::validate_utf16(&input).unwrap_err();
+            assert_eq!((err.offset(), err.violation()), (at, violation));
+        }
+    }
+
+    #[test]
+    fn test_utf32() {
+        assert_eq!(UnicodeAssignables::validate_utf32(&[0x61, 0x1f600]), Ok(None));
+        let bom = UnicodeAssignables::validate_utf32(&[0xfeff, 0x61, 0x1f600]);
+        assert_eq!(bom, Ok(Some(ByteOrder::NATIVE)));
+        let err = UnicodeScalars::validate_utf32(&[0x61, 0x110000]).unwrap_err();
+        assert_eq!((err.offset(), err.violation()), (1, Violation::OutOfRange));
+        // only a leading U+FEFF is a byte order mark
+        assert_eq!(UnicodeScalars::validate_utf32(&[0x61, 0xfeff]), Ok(None));
+
+        // byte swapped BOM
+        let swapped = [0xfffe0000, 0x61000000, 0x00f60100];
+        let bom = UnicodeAssignables::validate_utf32(&swapped);
+        assert_eq!(bom, Ok(Some(ByteOrder::NATIVE.swapped())));
+        let bom = UnicodeScalars::validate_utf32(&swapped[..1]);
+        assert_eq!(bom, Ok(Some(ByteOrder::NATIVE.swapped())));
+        let err = XmlCharacters::validate_utf32(&[0xfffe0000, 0x61000000, 0x1b000000]);
+        let err = err.unwrap_err();
+        assert_eq!((err.offset(), err.code_point()), (2, Some(0x1b)));
+        let err = UnicodeScalars::validate_utf32(&[0x61, 0xfffe0000]).unwrap_err();
+        assert_eq!((err.offset(), err.violation()), (1, Violation::OutOfRange));
+    }
+
+    #[test]
+    fn test_utf32_bytes() {
+        let le = [0xff, 0xfe, 0, 0, 0x61, 0, 0, 0, 0x00, 0xf6, 0x01, 0x00];
+        let be = [0, 0, 0xfe, 0xff, 0, 0, 0, 0x61, 0x00, 0x01, 0xf6, 0x00];
+        let bom = UnicodeAssignables::validate_utf32_bytes(&le, ByteOrder::BigEndian);
+        assert_eq!(bom, Ok(Some(ByteOrder::LittleEndian)));
+        let bom = UnicodeAssignables::validate_utf32_bytes(&be, ByteOrder::LittleEndian);
+        assert_eq!(bom, Ok(Some(ByteOrder::BigEndian)));
+        let bom = UnicodeAssignables::validate_utf32_bytes(&le[4..], ByteOrder::LittleEndian);
+        assert_eq!(bom, Ok(None));
+        let err = UnicodeAssignables::validate_utf32_bytes(&le[4..], ByteOrder::BigEndian);
+        assert_eq!(err.unwrap_err().violation(), Violation::OutOfRange);
+
+        let err = XmlCharacters::validate_utf32_bytes(&be[..10], ByteOrder::BigEndian);
+        let err = err.unwrap_err();
+        assert_eq!((err.offset(), err.violation()), (8, Violation::TruncatedUtf32));
+        let bad = [0xff, 0xfe, 0, 0, 0x61, 0, 0, 0, 0x1b, 0, 0, 0, 0x61];
+        let err = XmlCharacters::validate_utf32_bytes(&bad, ByteOrder::BigEndian);
+        let err = err.unwrap_err();
+        assert_eq!((err.offset(), err.code_point()), (8, Some(0x1b)));
+    }
+}
@@ -0,0 +1,127 @@
+use core::fmt;
+
+/// Reason why a code point isn't part of a subset
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Violation {
+    /// A high or low surrogate, see [`is_unicode_surrotate`](crate::is_unicode_surrotate)
+    Surrogate,
+    /// A C0 control other than `b'\n'`, `b'\r'` or `b'\t'`
+    C0Control,
+    /// A C1 control, in the `0x80..=0x9f` range
+    C1Control,
+    /// The DEL control character `0x7f`
+    Delete,
+    /// A noncharacter, see [`is_noncharacter`](crate::is_noncharacter)
+    Noncharacter,
+    /// A value above `0x10ffff`, the last Unicode code point
+    OutOfRange,
+    /// A byte that can't appear at its position in UTF-8 input, such as a
+    /// continuation byte without a lead byte or `0xf8..=0xff`
+    InvalidUtf8,
+    /// A UTF-8 sequence longer than needed for its code point
+    Utf8Overlong,
+    /// A UTF-8 sequence encoding a surrogate
+    Utf8EncodedSurrogate,
+    /// A UTF-8 sequence encoding a value above `0x10ffff`
+    Utf8OutOfRange,
+    /// A UTF-8 sequence cut short by another sequence or the end of the input
+    Utf8Truncated,
+    /// A high surrogate not followed by a low surrogate in UTF-16 input
+    LoneHighSurrogate,
+    /// A low surrogate not preceded by a high surrogate in UTF-16 input
+    LoneLowSurrogate,
+    /// UTF-32 input whose length in bytes isn't a multiple of 4
+    TruncatedUtf32,
+    /// A code point excluded by a subset for a reason not listed above
+    Excluded,
+}
+
+impl Violation {
+    /// Short human readable description of the violation
+    pub const fn description(&self) -> &'static str {
+        match self {
+            Violation::Surrogate => "surrogate code point",
+            Violation::C0Control => "legacy C0 control character",
+            Violation::C1Control => "legacy C1 control character",
+            Violation::Delete => "DEL control character",
+            Violation::Noncharacter => "noncharacter",
+            Violation::OutOfRange => "value outside of the Unicode code space",
+            Violation::InvalidUtf8 => "invalid UTF-8",
+            Violation::Utf8Overlong => "overlong UTF-8 sequence",
+            Violation::Utf8EncodedSurrogate => "UTF-8 encoded surrogate",
+            Violation::Utf8OutOfRange => "UTF-8 sequence outside of the Unicode code space",
+            Violation::Utf8Truncated => "truncated UTF-8 sequence",
+            Violation::LoneHighSurrogate => "lone high surrogate",
+            Violation::LoneLowSurrogate => "lone low surrogate",
+            Violation::TruncatedUtf32 => "truncated UTF-32 code unit",
+            Violation::Excluded => "code point excluded by the subset",
+        }
+    }
+
+    /// Stable identifier of the violation, such as `"C1Control"`, for
+    /// machine readable reports. Unlike the [`Debug`] output, it is part of
+    /// the API and won't change.
+    pub const fn code(&self) -> &'static str {
+        match self {
+            Violation::Surrogate => "Surrogate",
+            Violation::C0Control => "C0Control",
+            Violation::C1Control => "C1Control",
+            Violation::Delete => "Delete",
+            Violation::Noncharacter => "Noncharacter",
+            Violation::OutOfRange => "OutOfRange",
+            Violation::InvalidUtf8 => "InvalidUtf8",
+            Violation::Utf8Overlong => "Utf8Overlong",
+            Violation::Utf8EncodedSurrogate => "Utf8EncodedSurrogate",
+            Violation::Utf8OutOfRange => "Utf8OutOfRange",
+            Violation::Utf8Truncated => "Utf8Truncated",
+            Violation::LoneHighSurrogate => "LoneHighSurrogate",
+            Violation::LoneLowSurrogate => "LoneLowSurrogate",
+            Violation::TruncatedUtf32 => "TruncatedUtf32",
+            Violation::Excluded => "Excluded",
+        }
+    }
+
+    /// Best guess at why `c` was excluded from a subset, used when a subset
+    /// only provides a predicate
+    pub const fn guess(c: u32) -> Violation {
+        if c > 0x10ffff {
+            Violation::OutOfRange
+        } else if crate::is_unicode_surrotate(c) {
+            Violation::Surrogate
+        } else if c == 0x7f {
+            Violation::Delete
+        } else if crate::control::is_legacy_control(c) {
+            if crate::control::is_c0_control(c) {
+                Violation::C0Control
+            } else {
+                Violation::C1Control
+            }
+        } else if crate::is_noncharacter(c) {
+            Violation::Noncharacter
+        } else {
+            Violation::Excluded
+        }
+    }
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.description())
+    }
+}
+
+impl core::error::Error for Violation {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_code() {
+        assert_eq!(Violation::C0Control.code(), "C0Control");
+        assert_eq!(Violation::Utf8EncodedSurrogate.code(), "Utf8EncodedSurrogate");
+        assert_eq!(Violation::TruncatedUtf32.code(), "TruncatedUtf32");
+        assert_eq!(Violation::Excluded.code(), "Excluded");
+    }
+}